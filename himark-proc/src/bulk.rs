@@ -0,0 +1,218 @@
+//! Implementation of the `tuples!` and `arrays!` macros.
+
+use std::ops::RangeInclusive;
+
+use proc_macro2::{Span, TokenStream};
+use quote::format_ident;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{parse_quote, Token};
+
+use crate::himark_path;
+use crate::mark::{self, Marker, Mode};
+
+/// Arity range used when `tuples!` is given none, matching std.
+const DEFAULT_ARITIES: RangeInclusive<usize> = 0..=12;
+
+/// Input of the `tuples!` macro, e.g. `Pod, 1..=12`.
+pub(crate) struct Tuples {
+    pub marker: syn::Path,
+    pub arities: RangeInclusive<usize>,
+    pub strict: bool,
+}
+
+/// Input of the `arrays!` macro, e.g. `Pod, slices, refs`.
+pub(crate) struct Arrays {
+    pub marker: syn::Path,
+    /// Implement the marker for `[T]`.
+    pub slices: bool,
+    /// Implement the marker for `&T` and `&mut T`.
+    pub refs: bool,
+    pub strict: bool,
+}
+
+/// Parses `strict = bool` option, if input starts with one.
+fn parse_strict(input: ParseStream) -> syn::Result<Option<bool>> {
+    if !(input.peek(syn::Ident) && input.peek2(Token![=])) {
+        return Ok(None);
+    }
+    let option: syn::Ident = input.parse()?;
+    if option != "strict" {
+        return Err(syn::Error::new_spanned(option, "expected `strict = bool`"));
+    }
+    input.parse::<Token![=]>()?;
+    Ok(Some(input.parse::<syn::LitBool>()?.value))
+}
+
+/// Parses range of tuple arities, e.g. `0..=12` or `..13`.
+fn parse_arities(input: ParseStream) -> syn::Result<RangeInclusive<usize>> {
+    let range: syn::ExprRange = input.parse()?;
+    let bound = |expr: &Option<Box<syn::Expr>>| -> syn::Result<Option<usize>> {
+        match expr.as_deref() {
+            None => Ok(None),
+            Some(syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(lit),
+                ..
+            })) => lit.base10_parse().map(Some),
+            Some(expr) => Err(syn::Error::new_spanned(expr, "expected integer literal")),
+        }
+    };
+
+    let start = bound(&range.start)?.unwrap_or(0);
+    let end = match (bound(&range.end)?, &range.limits) {
+        (Some(end), syn::RangeLimits::Closed(_)) => end,
+        (Some(end), syn::RangeLimits::HalfOpen(_)) if end > start => end - 1,
+        (None, _) => {
+            return Err(syn::Error::new_spanned(
+                range,
+                "expected range with an upper bound",
+            ))
+        }
+        _ => return Err(syn::Error::new_spanned(range, "expected non-empty range")),
+    };
+    Ok(start..=end)
+}
+
+impl Parse for Tuples {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut tuples = Tuples {
+            marker: input.parse()?,
+            arities: DEFAULT_ARITIES,
+            strict: true,
+        };
+
+        while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            if let Some(strict) = parse_strict(input)? {
+                tuples.strict = strict;
+            } else {
+                tuples.arities = parse_arities(input)?;
+            }
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("expected `,`"));
+        }
+        Ok(tuples)
+    }
+}
+
+impl Parse for Arrays {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut arrays = Arrays {
+            marker: input.parse()?,
+            slices: false,
+            refs: false,
+            strict: true,
+        };
+
+        while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            if let Some(strict) = parse_strict(input)? {
+                arrays.strict = strict;
+                continue;
+            }
+            let option: syn::Ident = input.parse()?;
+            if option == "slices" {
+                arrays.slices = true;
+            } else if option == "refs" {
+                arrays.refs = true;
+            } else {
+                return Err(syn::Error::new_spanned(
+                    option,
+                    "expected `slices`, `refs` or `strict = bool`",
+                ));
+            }
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("expected `,`"));
+        }
+        Ok(arrays)
+    }
+}
+
+/// Implements the marker for `self_ty` whose parameters are bounded with it.
+fn blanket(
+    marker: &syn::Path,
+    strict: bool,
+    generics: syn::Generics,
+    self_ty: syn::Type,
+    span: Span,
+) -> TokenStream {
+    let markers = [Marker {
+        unsafety: None,
+        reason: None,
+        path: marker.clone(),
+        predicates: Vec::new(),
+    }];
+    mark::impls(
+        &himark_path(),
+        &markers,
+        Mode::new(strict),
+        &generics,
+        &self_ty,
+        &[],
+        span,
+    )
+}
+
+pub(crate) fn expand_tuples(input: Tuples) -> syn::Result<TokenStream> {
+    let Tuples {
+        marker,
+        arities,
+        strict,
+    } = input;
+    let span = marker.span();
+
+    let mut gen = TokenStream::new();
+    for arity in arities {
+        let params: Vec<_> = (0..arity).map(|i| format_ident!("T{}", i)).collect();
+        let generics = parse_quote! { <#(#params: #marker),*> };
+        let self_ty = parse_quote! { (#(#params,)*) };
+        gen.extend(blanket(&marker, strict, generics, self_ty, span));
+    }
+    Ok(gen)
+}
+
+pub(crate) fn expand_arrays(input: Arrays) -> syn::Result<TokenStream> {
+    let Arrays {
+        marker,
+        slices,
+        refs,
+        strict,
+    } = input;
+    let span = marker.span();
+
+    let mut gen = blanket(
+        &marker,
+        strict,
+        parse_quote! { <T: #marker, const N: usize> },
+        parse_quote! { [T; N] },
+        span,
+    );
+    if slices {
+        gen.extend(blanket(
+            &marker,
+            strict,
+            parse_quote! { <T: #marker> },
+            parse_quote! { [T] },
+            span,
+        ));
+    }
+    if refs {
+        gen.extend(blanket(
+            &marker,
+            strict,
+            parse_quote! { <'a, T: ?Sized + #marker> },
+            parse_quote! { &'a T },
+            span,
+        ));
+        gen.extend(blanket(
+            &marker,
+            strict,
+            parse_quote! { <'a, T: ?Sized + #marker> },
+            parse_quote! { &'a mut T },
+            span,
+        ));
+    }
+    Ok(gen)
+}
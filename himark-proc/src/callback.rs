@@ -0,0 +1,537 @@
+//! Protocol between `#[mark]` and callback macros generated by `#[marker]`.
+//!
+//! `#[mark]` invokes the callback of the first trait with the state of the expansion.
+//! Each callback passes the state to [`expand`] together with information about its trait,
+//! which implements implied supertraits and invokes the callback of the next trait.
+//! Traits without callback go through the no-op fallback, which continues without information.
+//! In strict mode the marked traits are implemented by their callbacks, so a trait without one
+//! is reported only as not being a marker instead of failing its impl as well.
+
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit_mut::{self, VisitMut};
+use syn::{braced, bracketed, parse_quote, LitStr, Token, TypeParamBound};
+
+use crate::mark::{display_tokens, ReplaceSelf};
+
+/// Auto traits, which are never implemented for implied supertraits.
+const AUTO_TRAITS: [&str; 5] = ["Send", "Sync", "Unpin", "UnwindSafe", "RefUnwindSafe"];
+
+/// Trait implementation which awaits its callback.
+pub(crate) struct Entry {
+    pub path: syn::Path,
+    pub impl_generics: TokenStream,
+    pub where_clause: TokenStream,
+    /// Implementation of the trait made by its callback, empty if it's already implemented.
+    pub item: TokenStream,
+}
+
+/// State of the `#[mark]` expansion passed through callbacks.
+pub(crate) struct State {
+    pub self_ty: syn::Type,
+    /// Traits whose callbacks are yet to be invoked, current one first.
+    pub todo: Vec<Entry>,
+    /// Keys of the implemented traits, see [`key`].
+    pub done: Vec<String>,
+    /// Implemented markers, listed by `himark::Marked` and registry entries.
+    pub markers: Vec<syn::Path>,
+    /// Implementation of `himark::Marked` made once all callbacks are invoked.
+    pub introspect: Option<Introspect>,
+    /// Whether some marker declared with `#[marker(registry)]` registers the type.
+    pub registered: bool,
+}
+
+/// Generics of the `himark::Marked` implementation.
+pub(crate) struct Introspect {
+    pub impl_generics: TokenStream,
+    pub where_clause: TokenStream,
+}
+
+/// Information about the trait which the callback was generated for.
+struct Info {
+    /// Module of the sealing token of a sealed marker, named from `$crate`.
+    seal: Option<syn::Path>,
+    exclusive: bool,
+    registry: bool,
+    params: syn::Generics,
+    implies: Punctuated<TypeParamBound, Token![+]>,
+}
+
+/// Input of the `chain!` macro.
+pub(crate) struct Chain {
+    himark: TokenStream,
+    state: State,
+    info: Option<Info>,
+}
+
+/// Key under which trait is deduplicated: the whole path with its generic arguments.
+///
+/// Paths of implied supertraits are resolved relative to the path of the marker which implied them,
+/// so traits are skipped only if they are named the same way as in `#[mark]`.
+/// Distinct traits which share the last segment, like `gfx::Array` and `other::Array`, are both implemented.
+pub(crate) fn key(path: &syn::Path) -> String {
+    display_tokens(path)
+}
+
+/// Checks whether path names an auto trait, which never has a callback.
+pub(crate) fn is_auto_trait(path: &syn::Path) -> bool {
+    path.segments
+        .last()
+        .is_some_and(|segment| AUTO_TRAITS.iter().any(|auto| segment.ident == auto))
+}
+
+impl ToTokens for Entry {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Entry {
+            path,
+            impl_generics,
+            where_clause,
+            item,
+        } = self;
+        tokens.extend(quote! { { [#path] [#impl_generics] [#where_clause] [#item] } });
+    }
+}
+
+impl ToTokens for State {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let State {
+            self_ty,
+            todo,
+            done,
+            markers,
+            introspect,
+            registered,
+        } = self;
+        tokens.extend(quote! {
+            [#self_ty] [#(#todo)*] [#(#done)*] [#([#markers])*] [#introspect] [#registered]
+        });
+    }
+}
+
+impl ToTokens for Introspect {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Introspect {
+            impl_generics,
+            where_clause,
+        } = self;
+        tokens.extend(quote! { [#impl_generics] [#where_clause] });
+    }
+}
+
+impl Parse for Introspect {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (impl_generics, where_clause);
+        bracketed!(impl_generics in input);
+        bracketed!(where_clause in input);
+        Ok(Introspect {
+            impl_generics: impl_generics.parse()?,
+            where_clause: where_clause.parse()?,
+        })
+    }
+}
+
+impl Parse for Entry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        braced!(content in input);
+        let (path, impl_generics, where_clause, item);
+        bracketed!(path in content);
+        bracketed!(impl_generics in content);
+        bracketed!(where_clause in content);
+        bracketed!(item in content);
+        Ok(Entry {
+            path: path.parse()?,
+            impl_generics: impl_generics.parse()?,
+            where_clause: where_clause.parse()?,
+            item: item.parse()?,
+        })
+    }
+}
+
+impl Parse for State {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (self_ty, todo, done, markers, introspect, registered);
+        bracketed!(self_ty in input);
+        bracketed!(todo in input);
+        bracketed!(done in input);
+        bracketed!(markers in input);
+        bracketed!(introspect in input);
+        bracketed!(registered in input);
+
+        let mut state = State {
+            self_ty: self_ty.parse()?,
+            todo: Vec::new(),
+            done: Vec::new(),
+            markers: Vec::new(),
+            introspect: if introspect.is_empty() {
+                None
+            } else {
+                Some(introspect.parse()?)
+            },
+            registered: registered.parse::<syn::LitBool>()?.value,
+        };
+        while !todo.is_empty() {
+            state.todo.push(todo.parse()?);
+        }
+        while !done.is_empty() {
+            state.done.push(done.parse::<LitStr>()?.value());
+        }
+        while !markers.is_empty() {
+            let marker;
+            bracketed!(marker in markers);
+            state.markers.push(marker.parse()?);
+        }
+        Ok(state)
+    }
+}
+
+impl Parse for Chain {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (himark, state);
+        bracketed!(himark in input);
+        bracketed!(state in input);
+
+        let info = if input.is_empty() {
+            None
+        } else {
+            let (seal, exclusive, registry, params, implies);
+            bracketed!(seal in input);
+            bracketed!(exclusive in input);
+            bracketed!(registry in input);
+            bracketed!(params in input);
+            bracketed!(implies in input);
+            Some(Info {
+                seal: if seal.is_empty() {
+                    None
+                } else {
+                    Some(seal.parse()?)
+                },
+                exclusive: exclusive.parse::<syn::LitBool>()?.value,
+                registry: registry.parse::<syn::LitBool>()?.value,
+                params: params.parse()?,
+                implies: Punctuated::parse_terminated(&implies)?,
+            })
+        };
+
+        Ok(Chain {
+            himark: himark.parse()?,
+            state: state.parse()?,
+            info,
+        })
+    }
+}
+
+/// Invokes callback of the first trait in `state`.
+///
+/// Callback is imported in the inner scope where it shadows the no-op fallback,
+/// so traits which don't provide one (auto traits, non-markers) continue without information.
+/// It's invoked in a nested block, otherwise its expansion could define the imported name,
+/// which gets resolution of traits without callback stuck.
+///
+/// Once there are no callbacks left, implements `himark::Marked` and registers the type if requested.
+pub(crate) fn invoke(himark: &TokenStream, state: &State) -> TokenStream {
+    let Some(current) = state.todo.first() else {
+        return finish(himark, state);
+    };
+
+    let mut import = current.path.clone();
+    if let Some(last) = import.segments.last_mut() {
+        last.arguments = syn::PathArguments::None;
+    }
+
+    quote! {
+        const _: () = {
+            #[allow(unused_imports)]
+            use #himark::__private::no_callback as __himark_callback;
+            {
+                #[allow(unused_imports)]
+                use #import as __himark_callback;
+                {
+                    __himark_callback! { [#himark] [#state] }
+                }
+            }
+        };
+    }
+}
+
+/// Implements `himark::Marked` and adds registry entry with markers collected in the state.
+fn finish(himark: &TokenStream, state: &State) -> TokenStream {
+    let State {
+        self_ty,
+        markers,
+        introspect,
+        registered,
+        ..
+    } = state;
+
+    let mut gen = TokenStream::new();
+    if let Some(Introspect {
+        impl_generics,
+        where_clause,
+    }) = introspect
+    {
+        gen.extend(quote! {
+            impl #impl_generics #himark::Marked for #self_ty #where_clause {
+                const MARKERS: &'static [#himark::MarkerInfo] = &[
+                    #(<dyn #markers as #himark::MarkerMeta>::INFO),*
+                ];
+
+                type Markers = #himark::Set![#(#markers),*];
+            }
+        });
+    }
+    if *registered {
+        gen.extend(quote! {
+            #himark::__private::registry! { @entry
+                const _: () = {
+                    #[#himark::__private::linkme::distributed_slice(#himark::__private::REGISTRY)]
+                    #[linkme(crate = #himark::__private::linkme)]
+                    static ENTRY: #himark::TypeEntry = #himark::TypeEntry::new::<#self_ty>(&[
+                        #(<dyn #markers as #himark::MarkerMeta>::INFO),*
+                    ]);
+                };
+            }
+        });
+    }
+    gen
+}
+
+pub(crate) fn expand(chain: Chain) -> syn::Result<TokenStream> {
+    let Chain {
+        himark,
+        mut state,
+        info,
+    } = chain;
+
+    if state.todo.is_empty() {
+        return Ok(finish(&himark, &state));
+    }
+    let current = state.todo.remove(0);
+    let Entry {
+        path: trait_name,
+        impl_generics,
+        where_clause,
+        item,
+    } = &current;
+    let self_ty = state.self_ty.clone();
+
+    let mut gen = TokenStream::new();
+    if let Some(info) = info {
+        gen.extend(item.clone());
+
+        // Only concrete types can be listed at runtime.
+        if info.registry && impl_generics.is_empty() {
+            state.registered = true;
+        }
+
+        // Glob import skips the token in other crates, where the local type takes its place
+        // and the marker's `Sealed` supertrait isn't implemented.
+        if let Some(seal) = &info.seal {
+            gen.extend(quote! {
+                const _: () = {
+                    #[allow(dead_code)]
+                    struct __HimarkToken;
+                    {
+                        #[allow(unused_imports)]
+                        use #seal::*;
+
+                        impl #impl_generics #himark::__private::Sealed<__HimarkToken> for #self_ty #where_clause {}
+                    }
+                };
+            });
+        }
+
+        if info.exclusive {
+            let mut generics: syn::Generics = syn::parse2(impl_generics.clone())?;
+            generics.params.push(parse_quote! { __HimarkO: ?Sized });
+            let (impl_generics, _, _) = generics.split_for_impl();
+            gen.extend(quote! {
+                impl #impl_generics #himark::__private::Excludes<#self_ty, __HimarkO>
+                    for dyn #trait_name #where_clause {}
+            });
+        }
+
+        let mut substitute = Substitute::new(&info.params, trait_name);
+        for bound in &info.implies {
+            let TypeParamBound::Trait(bound) = bound else {
+                continue;
+            };
+            let mut supertrait = bound.path.clone();
+            respan(&mut supertrait, trait_name.span());
+            let mut path = relative_to(trait_name, &supertrait);
+            substitute.visit_path_mut(&mut path);
+            ReplaceSelf(&self_ty).visit_path_mut(&mut path);
+
+            let key = key(&path);
+            if is_auto_trait(&path) || state.done.contains(&key) {
+                continue;
+            }
+
+            gen.extend(quote! {
+                impl #impl_generics #path for #self_ty #where_clause {}
+            });
+            state.markers.push(path.clone());
+            state.done.push(key);
+            state.todo.push(Entry {
+                path,
+                impl_generics: impl_generics.clone(),
+                where_clause: where_clause.clone(),
+                item: TokenStream::new(),
+            });
+        }
+    }
+
+    gen.extend(invoke(&himark, &state));
+    Ok(gen)
+}
+
+/// Resolves plain supertrait name relative to the module of the marker, as it was named in `#[mark]`.
+///
+/// Plain names refer to the module of the marker which is unknown to its callback,
+/// e.g. `#[mark(gfx::Matrix)]` implies `gfx::Uniform` for `trait Matrix: Uniform`.
+fn relative_to(marker: &syn::Path, supertrait: &syn::Path) -> syn::Path {
+    if supertrait.leading_colon.is_some() || supertrait.segments.len() != 1 {
+        return supertrait.clone();
+    }
+    let mut path = marker.clone();
+    path.segments.pop();
+    path.segments.extend(supertrait.segments.iter().cloned());
+    path
+}
+
+/// Points paths of implied supertraits at the marker which implied them.
+///
+/// `$crate` keeps its span, as it determines the crate which it refers to.
+fn respan(path: &mut syn::Path, span: proc_macro2::Span) {
+    for segment in path.segments.iter_mut() {
+        if segment.ident != "$crate" {
+            segment.ident.set_span(span);
+        }
+    }
+}
+
+/// Replaces generic parameters of the marker with arguments it was applied with.
+///
+/// Parameters without arguments are replaced with their defaults.
+struct Substitute {
+    types: HashMap<syn::Ident, syn::GenericArgument>,
+    lifetimes: HashMap<syn::Ident, syn::Lifetime>,
+}
+
+impl Substitute {
+    fn new(params: &syn::Generics, trait_name: &syn::Path) -> Self {
+        let args: Vec<&syn::GenericArgument> =
+            match trait_name.segments.last().map(|s| &s.arguments) {
+                Some(syn::PathArguments::AngleBracketed(args)) => args.args.iter().collect(),
+                _ => Vec::new(),
+            };
+        let mut lifetime_args = args.iter().filter_map(|arg| match arg {
+            syn::GenericArgument::Lifetime(lifetime) => Some(lifetime.clone()),
+            _ => None,
+        });
+        let mut other_args = args
+            .iter()
+            .filter(|arg| !matches!(arg, syn::GenericArgument::Lifetime(_)))
+            .map(|&arg| arg.clone());
+
+        let mut substitute = Substitute {
+            types: HashMap::new(),
+            lifetimes: HashMap::new(),
+        };
+        for param in &params.params {
+            match param {
+                syn::GenericParam::Lifetime(param) => {
+                    if let Some(lifetime) = lifetime_args.next() {
+                        substitute
+                            .lifetimes
+                            .insert(param.lifetime.ident.clone(), lifetime);
+                    }
+                }
+                syn::GenericParam::Type(param) => {
+                    let arg = other_args
+                        .next()
+                        .or_else(|| param.default.clone().map(syn::GenericArgument::Type));
+                    if let Some(arg) = arg {
+                        substitute.types.insert(param.ident.clone(), arg);
+                    }
+                }
+                syn::GenericParam::Const(param) => {
+                    let arg = other_args
+                        .next()
+                        .or_else(|| param.default.clone().map(syn::GenericArgument::Const));
+                    if let Some(arg) = arg {
+                        substitute.types.insert(param.ident.clone(), arg);
+                    }
+                }
+            }
+        }
+        substitute
+    }
+
+    /// Argument for the parameter named by `path`, if it is one.
+    fn lookup(&self, path: &syn::Path) -> Option<&syn::GenericArgument> {
+        path.get_ident().and_then(|ident| self.types.get(ident))
+    }
+}
+
+impl VisitMut for Substitute {
+    fn visit_generic_argument_mut(&mut self, arg: &mut syn::GenericArgument) {
+        // Const arguments which are single identifiers are parsed as types.
+        if let syn::GenericArgument::Type(syn::Type::Path(ty)) = arg {
+            if ty.qself.is_none() {
+                if let Some(replacement) = self.lookup(&ty.path) {
+                    *arg = match replacement {
+                        syn::GenericArgument::Const(expr) => {
+                            syn::GenericArgument::Const(syn::parse_quote! { { #expr } })
+                        }
+                        replacement => replacement.clone(),
+                    };
+                    return;
+                }
+            }
+        }
+        visit_mut::visit_generic_argument_mut(self, arg);
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let syn::Type::Path(path) = ty {
+            if path.qself.is_none() {
+                if let Some(syn::GenericArgument::Type(replacement)) = self.lookup(&path.path) {
+                    *ty = replacement.clone();
+                    return;
+                }
+            }
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::Path(path) = expr {
+            if path.qself.is_none() {
+                match self.lookup(&path.path) {
+                    Some(syn::GenericArgument::Const(replacement)) => {
+                        *expr = syn::parse_quote! { { #replacement } };
+                        return;
+                    }
+                    Some(syn::GenericArgument::Type(syn::Type::Path(replacement))) => {
+                        *expr = syn::parse_quote! { { #replacement } };
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut syn::Lifetime) {
+        if let Some(replacement) = self.lifetimes.get(&lifetime.ident) {
+            *lifetime = replacement.clone();
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Implementation of the `denmark!` macro and helper macros of markers.
+
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{bracketed, Token};
+
+use crate::himark_path;
+use crate::mark::{self, Marker, Mode};
+
+/// Input of the `denmark!` macro, entries are separated with semicolons.
+pub(crate) struct Input {
+    /// Check that traits are validated markers.
+    pub strict: bool,
+    pub entries: Vec<Entry>,
+}
+
+/// Single entry, e.g. `impl<T> [Vec<T>, Box<T>] as Pod, Tagged where T: Pod`.
+pub(crate) struct Entry {
+    pub generics: syn::Generics,
+    pub types: Vec<syn::Type>,
+    pub markers: Vec<Marker>,
+}
+
+/// Input of helper macros generated by `#[marker(macro = name)]`,
+/// e.g. `[himark] [path::to::Marker] TypeA, impl<T> TypeB<T>`.
+pub(crate) struct Apply {
+    himark: TokenStream,
+    marker: syn::Path,
+    targets: Punctuated<Target, Token![,]>,
+}
+
+/// Type passed to the helper macro, optionally preceded by generics, e.g. `impl<T: Pod> Vec<T>`.
+pub(crate) struct Target {
+    generics: syn::Generics,
+    ty: syn::Type,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut strict = true;
+        if input.peek(syn::Ident) && input.peek2(Token![=]) {
+            let option: syn::Ident = input.parse()?;
+            if option != "strict" {
+                return Err(syn::Error::new_spanned(
+                    option,
+                    "expected type or `strict = bool`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            strict = input.parse::<syn::LitBool>()?.value;
+            input.parse::<Token![;]>()?;
+        }
+
+        let mut entries = Vec::new();
+        while !input.is_empty() {
+            entries.push(input.parse()?);
+            if !input.is_empty() {
+                input.parse::<Token![;]>()?;
+            }
+        }
+
+        Ok(Input { strict, entries })
+    }
+}
+
+impl Parse for Entry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut generics = if input.parse::<Option<Token![impl]>>()?.is_some() {
+            input.parse()?
+        } else {
+            syn::Generics::default()
+        };
+
+        // `[u8]` and `[u8; 4]` are types, `[u8, u16]` is a list of them.
+        let types = if is_type_list(input) {
+            let content;
+            bracketed!(content in input);
+            Punctuated::<syn::Type, Token![,]>::parse_terminated(&content)?
+                .into_iter()
+                .collect()
+        } else {
+            vec![input.parse()?]
+        };
+
+        input.parse::<Token![as]>()?;
+        let mut markers = Vec::new();
+        loop {
+            markers.push(Marker {
+                unsafety: None,
+                reason: None,
+                path: input.parse()?,
+                predicates: Vec::new(),
+            });
+            if input.parse::<Option<Token![,]>>()?.is_none()
+                || input.is_empty()
+                || input.peek(Token![;])
+                || input.peek(Token![where])
+            {
+                break;
+            }
+        }
+
+        if let Some(where_clause) = input.parse::<Option<syn::WhereClause>>()? {
+            mark::move_bounds_to_where_clause(&mut generics);
+            generics
+                .make_where_clause()
+                .predicates
+                .extend(where_clause.predicates);
+        }
+
+        Ok(Entry {
+            generics,
+            types,
+            markers,
+        })
+    }
+}
+
+impl Parse for Apply {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (himark, marker);
+        bracketed!(himark in input);
+        bracketed!(marker in input);
+        Ok(Apply {
+            himark: himark.parse()?,
+            marker: marker.parse()?,
+            targets: Punctuated::parse_terminated(input)?,
+        })
+    }
+}
+
+impl Parse for Target {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let generics = if input.peek(Token![impl]) && input.peek2(Token![<]) {
+            input.parse::<Token![impl]>()?;
+            input.parse()?
+        } else {
+            syn::Generics::default()
+        };
+        Ok(Target {
+            generics,
+            ty: input.parse()?,
+        })
+    }
+}
+
+/// Checks whether input starts with square brackets which contain a top level comma.
+fn is_type_list(input: ParseStream) -> bool {
+    let Some((mut content, _, _)) = input.cursor().group(Delimiter::Bracket) else {
+        return false;
+    };
+    let mut depth = 0usize;
+    while let Some((token, next)) = content.token_tree() {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                '>' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => return true,
+                _ => {}
+            }
+        }
+        content = next;
+    }
+    false
+}
+
+pub(crate) fn expand(input: Input) -> syn::Result<TokenStream> {
+    let himark = himark_path();
+    let mut gen = TokenStream::new();
+    for entry in &input.entries {
+        for ty in &entry.types {
+            gen.extend(mark::impls(
+                &himark,
+                &entry.markers,
+                Mode::new(input.strict),
+                &entry.generics,
+                ty,
+                &[],
+                ty.span(),
+            ));
+        }
+    }
+    Ok(gen)
+}
+
+pub(crate) fn expand_apply(input: Apply) -> syn::Result<TokenStream> {
+    let markers = [Marker {
+        unsafety: None,
+        reason: None,
+        path: input.marker,
+        predicates: Vec::new(),
+    }];
+    let mut gen = TokenStream::new();
+    for target in &input.targets {
+        gen.extend(mark::impls(
+            &input.himark,
+            &markers,
+            Mode::Strict,
+            &target.generics,
+            &target.ty,
+            &[],
+            target.ty.span(),
+        ));
+    }
+    Ok(gen)
+}
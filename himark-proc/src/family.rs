@@ -0,0 +1,177 @@
+//! Implementation of the `markers!` macro.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_quote, Token, TypeParamBound};
+
+use crate::himark_path;
+use crate::mark::{self, Marker, Mode};
+use crate::marker;
+
+/// Input of the `markers!` macro, items are separated with semicolons.
+pub(crate) struct Input {
+    pub items: Vec<Item>,
+}
+
+/// Single item, e.g. `pub trait Color: Visible` or `pub struct Red, Green: Color`.
+pub(crate) enum Item {
+    Trait(Box<syn::ItemTrait>),
+    Structs(Structs),
+}
+
+/// Zero-sized types declared together and marked with the same markers.
+pub(crate) struct Structs {
+    pub attrs: Vec<syn::Attribute>,
+    pub vis: syn::Visibility,
+    pub idents: Vec<syn::Ident>,
+    pub markers: Vec<syn::Path>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+            if !input.is_empty() {
+                input.parse::<Token![;]>()?;
+            }
+        }
+        Ok(Input { items })
+    }
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis: syn::Visibility = input.parse()?;
+
+        let lookahead = input.lookahead1();
+        if lookahead.peek(Token![trait]) {
+            let trait_token: Token![trait] = input.parse()?;
+            let ident: syn::Ident = input.parse()?;
+            let mut generics: syn::Generics = input.parse()?;
+            let colon_token: Option<Token![:]> = input.parse()?;
+            let mut supertraits = Punctuated::<TypeParamBound, Token![+]>::new();
+            if colon_token.is_some() {
+                while !(input.is_empty() || input.peek(Token![;]) || input.peek(Token![where])) {
+                    supertraits.push_value(input.parse()?);
+                    match input.parse::<Option<Token![+]>>()? {
+                        Some(plus) => supertraits.push_punct(plus),
+                        None => break,
+                    }
+                }
+            }
+            generics.where_clause = input.parse()?;
+            let where_clause = &generics.where_clause;
+
+            Ok(Item::Trait(Box::new(parse_quote! {
+                #(#attrs)*
+                #vis #trait_token #ident #generics #colon_token #supertraits #where_clause {}
+            })))
+        } else if lookahead.peek(Token![struct]) {
+            input.parse::<Token![struct]>()?;
+            let idents = Punctuated::<syn::Ident, Token![,]>::parse_separated_nonempty(input)?;
+            let markers = if input.parse::<Option<Token![:]>>()?.is_some() {
+                Punctuated::<syn::Path, Token![+]>::parse_separated_nonempty(input)?
+                    .into_iter()
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            Ok(Item::Structs(Structs {
+                attrs,
+                vis,
+                idents: idents.into_iter().collect(),
+                markers,
+            }))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+/// Validates the trait like `#[marker]` does, taking its arguments from `#[marker(...)]` attributes.
+fn expand_trait(mut input_trait: syn::ItemTrait) -> syn::Result<TokenStream> {
+    let mut args = marker::Args::default();
+    let mut attrs = Vec::new();
+    for attr in input_trait.attrs {
+        if !attr.path().is_ident("marker") {
+            attrs.push(attr);
+        } else if let syn::Meta::List(_) = attr.meta {
+            attr.parse_args_with(args.parser())?;
+        }
+    }
+    input_trait.attrs = attrs;
+
+    marker::expand(args, input_trait)
+}
+
+/// Declares the types with derives of all traits which ZSTs can implement and marks them.
+fn expand_structs(structs: Structs) -> TokenStream {
+    let himark = himark_path();
+    let Structs {
+        attrs,
+        vis,
+        idents,
+        markers,
+    } = structs;
+
+    let markers: Vec<Marker> = markers
+        .into_iter()
+        .map(|path| Marker {
+            unsafety: None,
+            reason: None,
+            path,
+            predicates: Vec::new(),
+        })
+        .collect();
+
+    let derives = derives();
+    let mut gen = TokenStream::new();
+    for ident in &idents {
+        gen.extend(quote! {
+            #(#attrs)*
+            #derives
+            #vis struct #ident;
+        });
+        gen.extend(mark::impls(
+            &himark,
+            &markers,
+            Mode::Strict,
+            &syn::Generics::default(),
+            &parse_quote! { #ident },
+            &[],
+            ident.span(),
+        ));
+    }
+    gen
+}
+
+/// Derives of the zero-sized types, shared with `#[reify]`.
+pub(crate) fn derives() -> TokenStream {
+    quote! {
+        #[derive(
+            ::core::clone::Clone,
+            ::core::marker::Copy,
+            ::core::fmt::Debug,
+            ::core::default::Default,
+            ::core::cmp::PartialEq,
+            ::core::cmp::Eq,
+            ::core::hash::Hash,
+        )]
+    }
+}
+
+pub(crate) fn expand(input: Input) -> syn::Result<TokenStream> {
+    let mut gen = TokenStream::new();
+    for item in input.items {
+        match item {
+            Item::Trait(input_trait) => gen.extend(expand_trait(*input_trait)?),
+            Item::Structs(structs) => gen.extend(expand_structs(structs)),
+        }
+    }
+    Ok(gen)
+}
@@ -1,81 +1,329 @@
 //! Oh, hi `mark`.
-//! 
+//!
 //! For those who crave more ergonomic marker traits.
-//! 
+//!
 //! ## Introduction
 //! Marker traits are a common design pattern in Rust, used to denote certain properties or capabilities of types without requiring any additional implementation. However, managing marker traits can be tedious and often leads to boilerplate code. The `himark` crate aims to alleviate these issues by providing ergonomic utilities for working with marker traits.
-//! 
+//!
 //! ## About
 //! The `himark` crate simplifies the usage of marker traits in Rust by offering two main features:
-//! 
+//!
 //! 1. **Automatic Implementation Generation**: Use `himark::mark` to automatically generate `impl` blocks for marker traits.
 //! 2. **Trait Validation**: Use `himark::marker` to ensure that a trait meets the criteria for being a marker trait.
-//! 
+//!
 //! ## Usage
-//! 
+//!
 //! ### Generating Implementations for Marker Traits
 //! The `himark::mark` attribute macro generates implementations for specified marker traits, reducing the need for boilerplate code.
-//! 
+//!
 //! #### Example:
 //! ```rust
+//! # #[himark::marker] trait MyMarkerTrait {}
 //! use himark::mark;
-//! 
+//!
 //! #[mark(MyMarkerTrait)]
 //! struct MyStruct;
 //! ```
-//! 
+//!
 //! This will automatically generate the following implementation:
 //! ```rust
+//! # trait MyMarkerTrait {} struct MyStruct;
 //! impl MyMarkerTrait for MyStruct {}
 //! ```
-//! 
+//!
+//! By default `mark` is strict and reports an error for traits which were not validated with `himark::marker`. Pass `strict = false` to mark types with arbitrary empty traits.
+//!
+//! For generic types `bounds = "params"` requires every type parameter to implement the marker, while `bounds = "fields"` requires it from every field type which mentions a generic parameter.
+//!
+//! Unsafe traits, including `Send` and `Sync`, are implemented with `#[mark(unsafe Send, reason = "...")]`, where `reason` becomes the safety comment of the generated `unsafe impl`. To only check that the type implements them use `#[mark(assert Send + Sync)]`, which points at the field responsible for a violation. Types can opt out of auto traits with `#[mark(!Send, !Sync, !Unpin)]`, which adds a zero-sized `_phantom` field whose value is available as `Type::PHANTOM`.
+//!
+//! With `#[mark(..., introspect)]` the type implements `himark::Marked`, whose `MARKERS` describe each of its markers with name, module path and a stable id. Its `Markers` type lists them as a type-level set, e.g. `himark::Set![Uniform, Array]`, so generic code can require `T::Markers: himark::Contains<dyn Uniform, I>`.
+//!
 //! ### Validating Marker Traits
 //! The `himark::marker` attribute macro validates that a trait meets the criteria of being a marker trait, ensuring that it has no associated items and that all its super traits are also markers or auto traits.
-//! 
+//!
 //! #### Example:
 //! ```rust
-//! use himark::marker;
-//! 
-//! #[marker]
+//! #[himark::marker]
 //! trait MyMarkerTrait {}
 //! ```
-//! 
+//!
 //! This macro will produce a compile-time error if the trait does not meet the criteria for being a marker trait.
-//! 
+//!
+//! Validated markers describe themselves through `himark::MarkerMeta`, implemented for `dyn Trait`, whose constants hold the name, module path, doc summary, supertraits and flags such as `SEALED` or `EXCLUSIVE`.
+//!
+//! #### Sealed markers
+//! Markers declared with `#[himark::marker(sealed)]` cannot be implemented outside of the defining crate. Within the crate they are applied with `himark::mark`, which also implements the sealing trait. Sealed markers outside of the crate root specify their module with `module = crate::path::to::module`.
+//!
+//! ```rust
+//! #[himark::marker(sealed)]
+//! pub trait Gpu {}
+//!
+//! #[himark::mark(Gpu)]
+//! pub struct Texture;
+//! # fn main() {}
+//! ```
+//!
+//! #### Implied markers
+//! Markers declared with `#[himark::marker(implies)]` make `himark::mark` implement their marker supertraits as well, transitively. Traits which are already implemented by the same attribute are skipped.
+//!
+//! ```rust
+//! #[himark::marker]
+//! pub trait Array {}
+//!
+//! #[himark::marker(implies)]
+//! pub trait Uniform: Array {}
+//!
+//! // Implements both `Uniform` and `Array`.
+//! #[himark::mark(Uniform)]
+//! pub struct Color;
+//! ```
+//!
+//! #### Propagation
+//! Markers declared with `#[himark::marker(propagate(...))]` are implemented for std wrappers such as `&T`, `Box<T>`, `Option<T>` or `PhantomData<T>` whenever the wrapped type implements them.
+//!
+//! ```rust
+//! #[himark::marker(propagate(ref, all_smart_pointers, option))]
+//! pub trait Shareable {}
+//! ```
+//!
+//! #### Diagnostics
+//! Unsatisfied bounds on validated markers suggest marking the type with `#[himark::mark(...)]`.
+//! The error can be customized with `message`, `label` and `note` arguments.
+//!
+//! ```rust
+//! #[himark::marker(message = "`{Self}` can't be uploaded to the gpu", label = "not uniform")]
+//! pub trait Uniform {}
+//! ```
+//!
+//! #### Helper macros
+//! Markers declared with `#[himark::marker(macro = name)]` come with a macro which implements them for a list of types, including sealing and implied markers.
+//! Markers outside of the crate root specify their module with `module = crate::path::to::module`.
+//!
+//! ```rust
+//! #[himark::marker(macro = uniform)]
+//! pub trait Uniform {}
+//!
+//! pub struct Matrix<T>([T; 4]);
+//!
+//! uniform!(f32, impl<T: Copy> Matrix<T>);
+//! # fn main() {}
+//! ```
+//!
+//! #### Exclusive markers
+//! Markers declared with `#[himark::marker(excludes(...))]` can't be implemented for a type together with any of the excluded markers.
+//!
+//! ```rust
+//! #[himark::marker(excludes(Gpu))]
+//! pub trait Cpu {}
+//!
+//! #[himark::marker]
+//! pub trait Gpu {}
+//!
+//! // #[himark::mark(Cpu, Gpu)] would fail to compile.
+//! #[himark::mark(Cpu)]
+//! pub struct Buffer;
+//! ```
+//!
+//! #### Registry
+//! Markers declared with `#[himark::marker(registry)]` keep a runtime list of the concrete types marked with them, across all crates linked into the binary. The registry is built on [`linkme`](https://docs.rs/linkme) and requires the `registry` feature.
+//!
+//! ```rust
+//! #[himark::marker(registry)]
+//! pub trait Plugin {}
+//!
+//! #[himark::mark(Plugin)]
+//! pub struct Reverb;
+//!
+//! # fn main() {
+//! for entry in <dyn Plugin>::implementors() {
+//!     println!("{} is a plugin", entry.name());
+//! }
+//! # }
+//! ```
+//!
+//! ### Declaring marker families
+//! `himark::markers!` declares markers together with zero-sized types marked with them. Types derive `Clone`, `Copy`, `Debug`, `Default`, `PartialEq`, `Eq` and `Hash`, and `#[marker(...)]` attributes of traits take the arguments of `himark::marker`.
+//!
+//! ```rust
+//! himark::markers! {
+//!     #[marker(sealed)]
+//!     pub trait Color;
+//!
+//!     pub struct Red, Green, Blue: Color;
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! ### Marking foreign types
+//! Types which can't be annotated with `himark::mark`, e.g. ones from other crates, are marked with `himark::denmark!`.
+//!
+//! ```rust
+//! #[himark::marker]
+//! pub trait Pod {}
+//!
+//! himark::denmark! {
+//!     [u8, u16, u32, u64] as Pod;
+//!     impl<T: Copy, const N: usize> [T; N] as Pod where T: Pod;
+//! }
+//! ```
+//!
+//! Structural markers can be implemented for tuples and arrays of marked types with `himark::tuples!` and `himark::arrays!`.
+//!
+//! ```rust
+//! #[himark::marker]
+//! pub trait Pod {}
+//!
+//! // Tuples of up to 12 elements.
+//! himark::tuples!(Pod);
+//! // Arrays, slices and references.
+//! himark::arrays!(Pod, slices, refs);
+//! ```
+//!
+//! ### Reified enums
+//! `himark::reify` generates a zero-sized type for each unit variant of an enum, implementing a `NameMarker` trait whose `VALUE` is the variant. `himark::dispatch!` turns a runtime value into the corresponding type, so generic code runs without a hand-written `match`. The types are named like the variants and live in a module named like the enum in snake case, e.g. `format::Json`.
+//!
+//! ```rust
+//! #[himark::reify]
+//! #[derive(Debug)]
+//! pub enum Format {
+//!     Json,
+//!     Toml,
+//! }
+//!
+//! fn describe<F: FormatMarker>() -> String {
+//!     format!("{:?} has size {}", F::VALUE, core::mem::size_of::<F>())
+//! }
+//!
+//! # fn main() {
+//! let format = Format::Toml;
+//! let description = himark::dispatch!(format, |F: FormatMarker| describe::<F>());
+//! # }
+//! ```
+//!
+//! ### Asserting markers
+//! Marker sets of concrete types, including ones from other crates, can be pinned with `himark::assert_marked!` and `himark::assert_not_marked!`.
+//! Both work in item position as well as in function bodies and const blocks.
+//!
+//! ```rust
+//! #[himark::marker]
+//! pub trait Cpu {}
+//!
+//! #[himark::marker]
+//! pub trait Gpu {}
+//!
+//! #[himark::mark(Cpu)]
+//! pub struct Buffer;
+//!
+//! himark::assert_marked!(Buffer: Cpu + Send);
+//! himark::assert_not_marked!(Buffer: Gpu);
+//! ```
+//!
+//! ### Querying markers
+//! `himark::is_marked!` evaluates to a `const bool` telling whether a concrete type carries a marker and `himark::select!` picks an expression based on its markers.
+//! Queries are resolved where the macro is invoked, so for generic parameters they only see the declared bounds.
+//!
+//! ```rust
+//! #[himark::marker]
+//! pub trait Cpu {}
+//!
+//! #[himark::marker]
+//! pub trait Gpu {}
+//!
+//! #[himark::mark(Gpu)]
+//! pub struct Texture;
+//!
+//! const ON_GPU: bool = himark::is_marked!(Texture, Gpu);
+//! const DEVICE: &str = himark::select!(Texture { Cpu => "cpu", Gpu => "gpu", _ => "unknown" });
+//! ```
+//!
+//! ### Capability sets
+//! Markers can be elements of type-level sets, e.g. `himark::Caps![Read, Write]`, which parameterise types by their capabilities. `Has` bounds require a marker, `SubsetOf` allows narrowing conversions while `Without` and `Union` compute new sets. Stable Rust can't tell types apart, so these take positions of the markers as parameters which are inferred for concrete sets.
+//!
+//! ```rust
+//! use core::marker::PhantomData;
+//! use himark::{Has, SubsetOf};
+//!
+//! #[himark::marker]
+//! pub trait Read {}
+//!
+//! #[himark::marker]
+//! pub trait Write {}
+//!
+//! pub struct File<C>(PhantomData<C>);
+//!
+//! impl<C> File<C> {
+//!     pub fn read<I>(&self) where C: Has<dyn Read, I> {}
+//!
+//!     pub fn narrow<D: SubsetOf<C, Is>, Is>(self) -> File<D> {
+//!         File(PhantomData)
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let file: File<himark::Caps![Read, Write]> = File(PhantomData);
+//! let file: File<himark::Caps![Read]> = file.narrow();
+//! file.read();
+//! # }
+//! ```
+//!
 //! ### Recommended configuration
-//! 
+//!
 //! For best user experience we recommend importing `himark` as `hi` either with `use himark as hi;` or custom `Cargo.toml` configuration.
-//! 
+//!
 //! ```toml
 //! [dependencies]
 //! hi = { package = "himark", version = ... }
 //! ```
-//! 
+//!
 //! And write code as allows:
-//! 
+//!
 //! ```rust
 //! use himark as hi;
-//! 
-//! #[hi::mark(...)]
+//! # #[hi::marker] trait MyMarkerTrait {}
+//!
+//! #[hi::mark(MyMarkerTrait)]
 //! struct Foo { }
 //! ```
-//! 
+//!
 //! ## Features
 //! - **Automatic Implementation Generation**: Simplifies the process of implementing marker traits.
 //! - **Trait Validation**: Ensures that your marker traits conform to the expected structure.
-//! 
+//!
 //! ## Contributing
 //! Contributions are welcome! Please feel free to submit a pull request or open an issue on GitHub.
-//! 
+//!
 //! ## License
 //! This project is licensed under the MIT License. See the [LICENSE](LICENSE) file for details.
- 
 
 extern crate proc_macro;
 
+mod bulk;
+mod callback;
+mod denmark;
+mod family;
+mod mark;
+mod marker;
+mod reify;
+
 use proc_macro::TokenStream;
-use quote::{quote, quote_spanned};
-use syn::{parse_macro_input, ItemTrait};
+use proc_macro2::Span;
+use proc_macro_crate::{crate_name, FoundCrate};
+use quote::quote;
+use syn::parse_macro_input;
+
+/// Path to the `himark` runtime crate as seen from the crate being expanded.
+///
+/// Respects renames in `Cargo.toml` (e.g. `hi = { package = "himark" }`).
+fn himark_path() -> proc_macro2::TokenStream {
+    match crate_name("himark") {
+        Ok(FoundCrate::Name(name)) => {
+            let ident = syn::Ident::new(&name, Span::call_site());
+            quote! { ::#ident }
+        }
+        _ => quote! { ::himark },
+    }
+}
 
 #[proc_macro_attribute]
 /// Attribute for use on traits which verifies that they are indeed markers.
@@ -83,57 +331,735 @@ use syn::{parse_macro_input, ItemTrait};
 /// Trait is considered a marker if:
 /// - has no associated items
 /// - all its super traits are also markers or auto traits
-pub fn marker(_: TokenStream, input: TokenStream) -> TokenStream {
-    // Parse the input tokens into a syntax tree
-    let input_trait = parse_macro_input!(input as ItemTrait);
-
-    if !input_trait.items.is_empty() {
-        return syn::Error::new_spanned(
-            input_trait,
-            "The #[marker] attribute can only be applied to empty traits",
-        )
-        .to_compile_error()
-        .into();
-    }
+///
+/// Unsafe traits are accepted as well, their impls generated by `propagate` are unsafe too.
+///
+/// Super traits are checked at compile time. Both inline bounds and `where Self: ...` predicates are considered.
+/// Accepted auto traits are `Send`, `Sync`, `Unpin`, `UnwindSafe` and `RefUnwindSafe`,
+/// all other super traits must be annotated with `#[marker]` themselves.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Array {}
+///
+/// #[hi::marker]
+/// trait Uniform: Array + Send where Self: Sync {}
+/// ```
+///
+/// Std traits which aren't dyn compatible, like `Clone`, `Default` or `Ord`, are rejected up front.
+///
+/// ```compile_fail
+/// // error: `Clone` is not a marker trait, markers cannot require traits which aren't dyn compatible
+/// #[himark::marker]
+/// trait Foo: Clone {}
+/// ```
+///
+/// ## Sealed markers
+///
+/// `#[marker(sealed)]` prevents the marker from being implemented outside of the defining crate.
+/// Inside of it sealed markers are applied with `#[mark]`, which implements the sealing trait as well.
+/// `#[mark]` names the crate private sealing token through the marker's module, so sealed markers
+/// which aren't defined in the crate root have to specify it with `module = crate::path::to::module`.
+///
+/// ```
+/// use himark as hi;
+///
+/// mod gpu {
+///     #[himark::marker(sealed, module = crate::gpu)]
+///     pub trait Gpu {}
+/// }
+///
+/// #[hi::mark(gpu::Gpu)]
+/// struct Texture;
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// #[himark::marker(sealed)]
+/// pub trait Gpu {}
+///
+/// struct Texture;
+///
+/// impl Gpu for Texture {}
+/// ```
+///
+/// ## Implied markers
+///
+/// `#[marker(implies)]` makes `#[mark]` implement marker supertraits together with the marker,
+/// transitively and with the same where clause. Supertraits which are implemented by the same
+/// `#[mark]` attribute, either listed explicitly or implied by another marker, are skipped.
+/// Traits are compared by their whole path, resolved next to the marker, and generic arguments,
+/// so `#[mark(gfx::Matrix, other::Array)]` implements both `other::Array` and `gfx::Array`.
+/// Auto traits are never implemented.
+///
+/// Supertraits are named in the crate of the marked item. Paths starting with `crate::`
+/// refer to the crate of the marker, plain names are resolved next to the marker as it was
+/// named in `#[mark]`, e.g. `#[mark(gfx::Matrix)]` implies `gfx::Uniform`. When the marker
+/// is imported, its plain supertraits have to be imported as well.
+/// Implied markers are only resolved by strict `#[mark]`.
+///
+/// ```
+/// use himark as hi;
+///
+/// mod gfx {
+///     #[himark::marker]
+///     pub trait Array {}
+///
+///     #[himark::marker(implies)]
+///     pub trait Uniform: Array {}
+///
+///     #[himark::marker(implies)]
+///     pub trait Matrix<const N: usize>: Uniform + Square<N> {}
+///
+///     #[himark::marker]
+///     pub trait Square<const N: usize> {}
+/// }
+///
+/// // Implements `Matrix<4>`, `Uniform`, `Array` and `Square<4>`.
+/// #[hi::mark(gfx::Matrix<4>)]
+/// struct Transform;
+///
+/// fn assert_marked<T: gfx::Array + gfx::Square<4>>() {}
+/// assert_marked::<Transform>();
+/// ```
+///
+/// Supertraits named with `self::` or `super::` paths cannot be implied.
+///
+/// ```compile_fail
+/// mod gfx {
+///     #[himark::marker]
+///     pub trait Array {}
+///
+///     #[himark::marker(implies)]
+///     pub trait Uniform: self::Array {}
+/// }
+/// ```
+///
+/// ## Propagation
+///
+/// `#[marker(propagate(...))]` implements the marker for std wrappers whenever the wrapped type implements it.
+/// Supported wrappers are `ref` (`&T`), `ref_mut` (`&mut T`), `box`, `rc`, `arc`, `option`, `phantom` (`PhantomData<T>`),
+/// `manually_drop`, `slice` (`[T]`) and `array` (`[T; N]`), as well as presets `all_refs`, `all_smart_pointers`
+/// (`box`, `rc` and `arc`) and `all`.
+///
+/// ```
+/// use himark as hi;
+/// use std::sync::Arc;
+///
+/// #[hi::marker(propagate(all_refs, all_smart_pointers, option))]
+/// trait Shareable {}
+///
+/// #[hi::mark(Shareable)]
+/// struct Config;
+///
+/// fn assert_shareable<T: Shareable>() {}
+/// assert_shareable::<Option<Arc<&Config>>>();
+/// ```
+///
+/// ## Diagnostics
+///
+/// Bounds on validated markers which aren't satisfied are reported with a message suggesting `#[himark::mark]`.
+/// It can be customized with `message`, `label` and `note` arguments which accept `#[diagnostic::on_unimplemented]`
+/// format strings, e.g. `{Self}` or names of the marker's parameters. Markers which declare their own
+/// `#[diagnostic::on_unimplemented]` attribute are left untouched.
+///
+/// ```compile_fail
+/// use himark as hi;
+///
+/// #[hi::marker(message = "`{Self}` can't be uploaded to the gpu", label = "not uniform")]
+/// trait Uniform {}
+///
+/// fn upload<T: Uniform>(_: T) {}
+///
+/// // error[E0277]: `String` can't be uploaded to the gpu
+/// upload(String::new());
+/// ```
+///
+/// ## Helper macros
+///
+/// `#[marker(macro = name)]` generates a macro which implements the marker for a list of types,
+/// e.g. `name!(Buffer, Matrix<u8>, impl<T: Copy> Vec<T>)`, the same way `#[mark]` does,
+/// including sealing and implied supertraits. It's exported together with the marker, except for sealed markers.
+///
+/// Macro expands in other modules and crates, so markers which aren't defined in the crate root
+/// have to specify their module with `module = crate::path::to::module`.
+/// Helper macros can't be generated for generic markers.
+///
+/// ```
+/// mod gfx {
+///     use himark as hi;
+///
+///     #[hi::marker]
+///     pub trait Array {}
+///
+///     #[hi::marker(implies, macro = uniform, module = crate::gfx)]
+///     pub trait Uniform: Array {}
+/// }
+///
+/// struct Matrix<T>([T; 4]);
+///
+/// gfx::uniform!(f32, impl<T: Copy> Matrix<T>);
+///
+/// fn main() {
+///     fn assert_uniform<T: gfx::Uniform + gfx::Array>() {}
+///     assert_uniform::<Matrix<u8>>();
+/// }
+/// ```
+///
+/// ## Exclusive markers
+///
+/// `#[marker(excludes(...))]` makes it a compile error to mark a type with both the marker and any of the excluded ones.
+/// Exclusive markers can only be implemented with `#[mark]` or `denmark!` and can't be generic.
+/// They can't propagate through `ref`, `ref_mut` and `box`, since other crates could mark those wrappers of their types.
+///
+/// ```compile_fail
+/// use himark as hi;
+///
+/// #[hi::marker(excludes(Gpu))]
+/// trait Cpu {}
+///
+/// #[hi::marker]
+/// trait Gpu {}
+///
+/// // error[E0119]: conflicting implementations of trait `Excludes<Buffer, dyn Gpu>` for type `dyn Cpu`
+/// #[hi::mark(Cpu, Gpu)]
+/// struct Buffer;
+/// ```
+///
+/// ## Registry
+///
+/// `#[marker(registry)]` makes `#[mark]` and `denmark!` register marked types in a linker section,
+/// so `<dyn Trait>::implementors()` lists [`himark::TypeEntry`] of every type marked with it in the final binary,
+/// including ones from other crates, in no particular order. Only concrete types are registered,
+/// generic impls and types marked with `strict = false` are not. Registry can't be used with generic markers.
+/// It requires the `registry` feature of `himark`, without it `#[marker(registry)]` is a compile error.
+///
+/// [`himark::TypeEntry`]: https://docs.rs/himark/latest/himark/struct.TypeEntry.html
+///
+/// ```
+/// use std::any::Any;
+/// use himark as hi;
+///
+/// #[hi::marker(registry)]
+/// trait Gpu {}
+///
+/// #[hi::mark(Gpu)]
+/// struct Texture;
+///
+/// #[hi::mark(Gpu)]
+/// struct Buffer<T>(T);
+///
+/// hi::denmark!(u32 as Gpu);
+///
+/// let mut names: Vec<_> = <dyn Gpu>::implementors().map(|entry| entry.name()).collect();
+/// names.sort();
+/// assert_eq!(names.len(), 2);
+/// assert!(names[0].ends_with("Texture") && names[1] == "u32");
+///
+/// let value: &dyn Any = &Texture;
+/// assert!(<dyn Gpu>::is_implemented_by(value.type_id()));
+/// ```
+pub fn marker(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut marker_args = marker::Args::default();
+    let parser = marker_args.parser();
+    parse_macro_input!(args with parser);
 
-    let gen = quote! {
-        #input_trait
-    };
+    // Parse the input tokens into a syntax tree
+    let input_trait = parse_macro_input!(input as syn::ItemTrait);
 
-    gen.into()
+    marker::expand(marker_args, input_trait)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
 #[proc_macro_attribute]
 /// Attribute for marking types with marker traits.
+///
+/// By default `#[mark]` is strict: each trait must be validated with `#[marker]` (or be an auto trait),
+/// otherwise an error is reported at the offending trait path.
+/// Use `strict = false` to mark types with arbitrary empty traits.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Array {}
+///
+/// trait Plain {}
+///
+/// #[hi::mark(Array)]
+/// struct Strict;
+///
+/// #[hi::mark(strict = false, Plain)]
+/// struct Lenient;
+/// ```
+///
+/// ```compile_fail
+/// use std::fmt::Debug;
+///
+/// // error[E0277]: `Debug` is not a marker trait
+/// #[himark::mark(Debug)]
+/// struct Foo;
+/// ```
+///
+/// ```compile_fail
+/// // error: `Clone` is not a marker trait
+/// #[himark::mark(Clone)]
+/// struct Foo;
+/// ```
+///
+/// Trait paths may have generic arguments, which can refer to the item's own parameters and `Self`.
+///
+/// ```
+/// use himark as hi;
+/// use std::marker::PhantomData;
+///
+/// #[hi::marker]
+/// trait Layout<const N: usize> {}
+///
+/// #[hi::marker]
+/// trait Backend<B: ?Sized> {}
+///
+/// #[hi::mark(Layout<3>, Backend<T>, Backend<Self>)]
+/// struct Buffer<T>(PhantomData<T>);
+/// ```
+///
+/// Each trait can be followed by a where clause which applies only to its impl.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Pod {}
+///
+/// #[hi::marker]
+/// trait Tagged {}
+///
+/// #[hi::mark(Pod where T: Pod, Tagged)]
+/// struct Wrapper<T>(T);
+/// ```
+///
+/// ## Bound inference
+///
+/// Like std derives, `bounds = "params"` adds `T: Trait` for every type parameter of the item.
+/// `bounds = "fields"` instead adds `Field: Trait` for every field type which mentions
+/// a type or const parameter. The inferred bounds apply to all traits of the attribute.
+///
+/// ```
+/// use himark as hi;
+/// use std::marker::PhantomData;
+///
+/// #[hi::marker]
+/// trait Pod {}
+///
+/// impl Pod for u8 {}
+///
+/// // impl<T> Pod for Tagged<T> where T: Pod {}
+/// #[hi::mark(Pod, bounds = "params")]
+/// struct Tagged<T>(PhantomData<T>);
+///
+/// // impl<T, const N: usize> Pod for Packet<T, N> where [T; N]: Pod, T: Pod {}
+/// #[hi::mark(Pod, bounds = "fields")]
+/// enum Packet<T, const N: usize> {
+///     Data([T; N]),
+///     Single { value: T, tag: u8 },
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use himark as hi;
+/// # #[hi::marker] trait Pod {}
+/// #[hi::mark(Pod, bounds = "fields")]
+/// struct Boxed<T>(Box<T>);
+///
+/// fn assert_pod<T: Pod>() {}
+/// assert_pod::<Boxed<u8>>(); // `Box<u8>: Pod` does not hold
+/// ```
+///
+/// Note that `bounds = "fields"` on a recursive type (e.g. `struct List<T>(Option<Box<List<T>>>)`)
+/// produces a cyclic requirement which the compiler cannot prove.
+///
+/// ## Introspection
+///
+/// `introspect` implements [`himark::Marked`] which lists [`himark::MarkerInfo`] of every marker,
+/// including implied ones. Markers are listed regardless of their where clauses.
+/// Its `Markers` type holds the same markers as a type-level set, queried with [`himark::Contains`].
+///
+/// [`himark::Marked`]: https://docs.rs/himark/latest/himark/trait.Marked.html
+/// [`himark::MarkerInfo`]: https://docs.rs/himark/latest/himark/struct.MarkerInfo.html
+/// [`himark::Contains`]: https://docs.rs/himark/latest/himark/trait.Contains.html
+///
+/// ```
+/// use himark::{self as hi, Contains, Marked};
+///
+/// #[hi::marker]
+/// trait Array {}
+///
+/// #[hi::marker(implies)]
+/// trait Uniform: Array {}
+///
+/// #[hi::mark(Uniform, introspect)]
+/// struct Matrix;
+///
+/// let names: Vec<_> = Matrix::MARKERS.iter().map(|marker| marker.name).collect();
+/// assert_eq!(names, ["Uniform", "Array"]);
+///
+/// fn is_array<T: Marked, I>(_: T)
+/// where
+///     T::Markers: Contains<dyn Array, I>,
+/// {
+/// }
+///
+/// is_array(Matrix);
+/// ```
+///
+/// ## Unsafe markers
+///
+/// Unsafe traits, including `Send` and `Sync`, are implemented with `unsafe Trait`.
+/// Attribute has to justify them with `reason = "..."`, which becomes the safety comment of the generated impls.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// unsafe trait Pod {}
+///
+/// // /// SAFETY: handle is never dereferenced.
+/// // unsafe impl Send for Handle {}
+/// #[hi::mark(unsafe Send, unsafe Pod, reason = "handle is never dereferenced")]
+/// struct Handle(*const u8);
+/// ```
+///
+/// Marking with `Send` or `Sync` without `unsafe` is an error, use `assert Send` to check them instead.
+///
+/// ```compile_fail
+/// use himark as hi;
+///
+/// // error: `Send` can only be implemented with `unsafe Send` and `reason = "..."`
+/// #[hi::mark(Send)]
+/// struct Handle(*const u8);
+/// ```
+///
+/// ## Opting out of auto traits
+///
+/// `!Send`, `!Sync` and `!Unpin` add a zero-sized `_phantom` field to the struct, which opts it out of the traits.
+/// Value of the field is available as `Struct::PHANTOM`. Unit structs are rejected, since the field would make
+/// them braced and break their construction and patterns. Declare them as `struct Token {}` instead.
+/// Place `#[mark]` above derives, so that they see the added field.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::mark(!Send, !Sync)]
+/// #[derive(Debug, Clone)]
+/// struct Handle {
+///     id: u32,
+/// }
+///
+/// #[hi::mark(!Unpin)]
+/// struct Pinned(u8);
+///
+/// #[hi::mark(!Sync)]
+/// struct Token {}
+///
+/// let handle = Handle { id: 0, _phantom: Handle::PHANTOM };
+/// let pinned = Pinned(0, Pinned::PHANTOM);
+/// let token = Token { _phantom: Token::PHANTOM };
+///
+/// hi::assert_not_marked!(Handle: Send);
+/// hi::assert_not_marked!(Token: Sync);
+/// ```
+///
+/// ```compile_fail
+/// use himark as hi;
+///
+/// // error: unit structs cannot opt out of auto traits, declare a braced struct instead, e.g. `struct Token {}`
+/// #[hi::mark(!Sync)]
+/// struct Token;
+/// ```
+///
+/// ## Assertions
+///
+/// `assert Trait + Other` checks that the item implements traits instead of implementing them.
+/// Type parameters of the item are assumed to implement the asserted traits,
+/// e.g. `#[mark(assert Send)]` on `Foo<T>` checks that `Foo<T>: Send` given `T: Send`.
+/// Auto traits are asserted for every field, so errors point at the field responsible.
+/// Note that this rejects types whose auto traits are implemented manually.
+///
+/// ```
+/// use himark as hi;
+/// use std::sync::Arc;
+///
+/// #[hi::mark(assert Send + Sync)]
+/// struct Shared<T> {
+///     value: Arc<T>,
+///     len: usize,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use himark as hi;
+/// use std::rc::Rc;
+///
+/// #[hi::mark(assert Send)]
+/// struct Shared<T> {
+///     // error[E0277]: `Rc<T>` cannot be sent between threads safely
+///     value: Rc<T>,
+/// }
+/// ```
 pub fn mark(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mark_args = parse_macro_input!(args as mark::Args);
+
     let parsed_input = parse_macro_input!(input as syn::DeriveInput);
 
-    let mut trait_names = Vec::new();
+    mark::expand(mark_args, parsed_input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    // Parser to collect the trait names from the attribute arguments
-    let parser = syn::meta::parser(|meta| match meta.value() {
-        Err(_) => {
-            trait_names.push(meta.path);
-            Ok(())
-        }
-        Ok(_) => Err(meta.error("expected trait name")),
-    });
+#[proc_macro]
+/// Implements marker traits for types which can't be annotated with `#[mark]`, e.g. foreign ones.
+///
+/// Each entry lists types followed by `as` and traits to implement, entries are separated with semicolons.
+/// Multiple types are given in square brackets, which otherwise denote slice and array types.
+/// Generic entries start with `impl<...>` and may end with a where clause which applies to all of their impls.
+///
+/// Like `#[mark]`, `denmark!` is strict unless it starts with `strict = false;`.
+/// Sealed and implied markers are handled as well. The fallback used without the `attrs` feature
+/// takes a single entry and ignores `strict = ...;`.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Integer {}
+///
+/// #[hi::marker]
+/// trait Pod {}
+///
+/// trait Plain {}
+///
+/// hi::denmark! {
+///     [u8, u16, u32, u64] as Integer, Pod;
+///     impl<T: Pod, const N: usize> [T; N] as Pod;
+///     impl<T: Copy> Vec<T> as Pod where T: Pod;
+/// }
+///
+/// hi::denmark!(strict = false; [u8] as Plain);
+///
+/// fn assert_pod<T: Pod>() {}
+/// assert_pod::<[u8; 4]>();
+/// assert_pod::<Vec<u32>>();
+/// ```
+pub fn denmark(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as denmark::Input);
+
+    denmark::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+/// Declares a family of markers together with zero-sized types marked with them.
+///
+/// Traits are declared as `pub trait Name: Supertraits;` and validated like with `#[marker]`,
+/// whose arguments are given in a `#[marker(...)]` attribute of the trait.
+/// Types are declared as `pub struct A, B, C: Marker + Other;`, they derive `Clone`, `Copy`, `Debug`, `Default`,
+/// `PartialEq`, `Eq` and `Hash` and are marked like with `#[mark]`. Attributes of the item apply to all of its types.
+/// Items are separated with semicolons.
+///
+/// ```
+/// use himark as hi;
+///
+/// hi::markers! {
+///     /// Colors of the palette.
+///     #[marker(sealed)]
+///     pub trait Color;
+///
+///     #[marker(implies)]
+///     pub trait Primary: Color;
+///
+///     pub struct Red, Green, Blue: Primary;
+///     pub struct Orange: Color;
+/// }
+///
+/// fn name<C: Color + core::fmt::Debug>(color: C) -> String {
+///     format!("{:?}", color)
+/// }
+///
+/// # fn main() {
+/// assert_eq!(name(Red), "Red");
+/// assert_eq!(Orange::default(), Orange);
+/// # }
+/// ```
+pub fn markers(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as family::Input);
 
+    family::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+/// Implements marker trait for tuples whose elements all implement it.
+///
+/// Takes the trait and optionally a range of tuple arities, which defaults to `0..=12`.
+/// Like `#[mark]`, `tuples!` is strict unless given `strict = false`.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Pod {}
+///
+/// hi::denmark!([u8, u16] as Pod);
+///
+/// // impl<T0: Pod, T1: Pod> Pod for (T0, T1) {}, etc.
+/// hi::tuples!(Pod, 1..=4);
+///
+/// fn assert_pod<T: Pod>() {}
+/// assert_pod::<(u8, (u16, u8))>();
+/// ```
+pub fn tuples(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as bulk::Tuples);
+
+    bulk::expand_tuples(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+/// Implements marker trait for arrays `[T; N]` whose elements implement it.
+///
+/// Options `slices` and `refs` additionally implement the trait for `[T]`, and `&T` and `&mut T` respectively.
+/// Like `#[mark]`, `arrays!` is strict unless given `strict = false`.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Pod {}
+///
+/// hi::denmark!([u8, u16] as Pod);
+///
+/// // impl<T: Pod, const N: usize> Pod for [T; N] {}
+/// hi::arrays!(Pod, slices, refs);
+///
+/// fn assert_pod<T: ?Sized + Pod>() {}
+/// assert_pod::<[[u8; 4]; 2]>();
+/// assert_pod::<&[u16]>();
+/// ```
+pub fn arrays(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as bulk::Arrays);
+
+    bulk::expand_arrays(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+/// Bridges a runtime enum with types, generating a zero-sized type for each of its unit variants.
+///
+/// For `enum Format` it generates a sealed `FormatMarker` trait with `const VALUE: Format`,
+/// implemented by the types, which derive the same traits as ones of `markers!`.
+/// Since `FormatMarker` has an associated constant, it's not a `#[marker]` which has to be empty.
+///
+/// The types are named like the variants and declared in a module named like the enum in snake case,
+/// e.g. `format::Json`, so they don't clash with other items or variants imported with `use Format::*`.
+/// Keywords get a trailing underscore, e.g. `type_::Unit` for `enum Type`.
+///
+/// [`dispatch!`] turns a runtime value into the corresponding type. It expands in other modules and crates,
+/// so dispatched enums which aren't defined in the crate root have to specify their module with `module = crate::path::to::module`.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::reify]
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// pub enum Format {
+///     Json,
+///     Cbor,
+///     Toml,
+/// }
+///
+/// fn extension<F: FormatMarker>() -> String {
+///     format!("{:?}", F::VALUE).to_lowercase()
+/// }
+///
+/// # fn main() {
+/// assert_eq!(format::Json::VALUE, Format::Json);
+///
+/// let format = Format::Cbor;
+/// let extension = hi::dispatch!(format, |F: FormatMarker| extension::<F>());
+/// assert_eq!(extension, "cbor");
+/// # }
+/// ```
+pub fn reify(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut reify_args = reify::Args::default();
+    let parser = reify_args.parser();
     parse_macro_input!(args with parser);
 
-    let ident = &parsed_input.ident;
-    let span = parsed_input.ident.span();
-    let generics = &parsed_input.generics;
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let input_enum = parse_macro_input!(input as syn::ItemEnum);
 
-    let impls = trait_names.iter().map(|trait_name| {
-        quote_spanned! {span=>
-            impl #impl_generics #trait_name for #ident #ty_generics #where_clause {}
-        }
-    });
+    reify::expand(reify_args, input_enum)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+/// Matches a runtime value of an enum annotated with [`macro@reify`] and evaluates the body with the type of its variant.
+///
+/// The marker generated for the enum is named in the closure-like syntax, e.g. `|F: FormatMarker| body`.
+/// Body is instantiated for every variant, so all of them have to evaluate to the same type.
+///
+/// ```
+/// mod config {
+///     #[himark::reify(module = crate::config)]
+///     pub enum Level {
+///         Low,
+///         High,
+///     }
+/// }
+///
+/// use config::LevelMarker;
+///
+/// fn threshold<L: LevelMarker>() -> u32 {
+///     (L::VALUE as u32 + 1) * 10
+/// }
+///
+/// fn main() {
+///     let level = config::Level::High;
+///     assert_eq!(himark::dispatch!(&level, |L: LevelMarker| threshold::<L>()), 20);
+/// }
+/// ```
+pub fn dispatch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as reify::Dispatch);
+
+    reify::expand_dispatch(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+/// Continues `#[mark]` expansion from the callback of a marker, not public API.
+pub fn __chain(input: TokenStream) -> TokenStream {
+    let chain = parse_macro_input!(input as callback::Chain);
+
+    callback::expand(chain)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let mut gen = quote::quote! { #parsed_input };
-    gen.extend(impls);
+#[doc(hidden)]
+#[proc_macro]
+/// Implements marker for types passed to its helper macro, not public API.
+pub fn __apply(input: TokenStream) -> TokenStream {
+    let apply = parse_macro_input!(input as denmark::Apply);
 
-    gen.into()
+    denmark::expand_apply(apply)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -0,0 +1,668 @@
+//! Implementation of the `#[mark]` attribute.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+use syn::{parse_quote, DeriveInput, Token, WherePredicate};
+
+use crate::callback::{self, Entry, Introspect, State};
+use crate::himark_path;
+
+/// Arguments of the `#[mark(...)]` attribute.
+pub(crate) struct Args {
+    /// Traits to implement for the annotated item.
+    pub markers: Vec<Marker>,
+    /// Check that traits are validated markers.
+    pub strict: bool,
+    /// Implement `himark::Marked` listing the markers.
+    pub introspect: bool,
+    /// Bounds inferred for every generated impl.
+    pub bounds: Bounds,
+    /// Justification of the `unsafe` markers.
+    pub reason: Option<syn::LitStr>,
+    /// Traits which are asserted instead of implemented, e.g. `assert Send + Sync`.
+    pub assertions: Vec<syn::Path>,
+    /// Auto traits which the item opts out of, e.g. `!Send`.
+    pub negative: Vec<syn::Ident>,
+}
+
+/// Auto traits whose impls are unsafe.
+const UNSAFE_AUTO_TRAITS: [&str; 2] = ["Send", "Sync"];
+
+/// Auto traits which can be opted out of with `!Trait`, in the order of their phantom fields.
+const NEGATIVE_AUTO_TRAITS: [&str; 3] = ["Send", "Sync", "Unpin"];
+
+/// Name of the field added by `!Trait` arguments.
+const PHANTOM_FIELD: &str = "_phantom";
+
+/// How [`impls`] checks and describes implemented markers.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// Any trait can be implemented.
+    Lenient,
+    /// Traits must be validated markers, their callbacks are invoked.
+    Strict,
+    /// Like `Strict`, additionally implements `himark::Marked` listing the markers.
+    Introspect,
+}
+
+impl Mode {
+    pub fn new(strict: bool) -> Self {
+        if strict {
+            Mode::Strict
+        } else {
+            Mode::Lenient
+        }
+    }
+}
+
+/// Bound inference mode selected with `bounds = "..."`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Bounds {
+    /// Impls are not constrained beyond the item's own bounds.
+    None,
+    /// Every type parameter implements the trait, like in std derives.
+    Params,
+    /// Every field type which mentions a generic parameter implements the trait.
+    Fields,
+}
+
+/// Single trait to implement, e.g. `Pod<T> where T: Pod`.
+pub(crate) struct Marker {
+    /// Marker is an unsafe trait, e.g. `unsafe Send`.
+    pub unsafety: Option<Token![unsafe]>,
+    /// Safety comment attached to the impl of an unsafe trait.
+    pub reason: Option<syn::LitStr>,
+    pub path: syn::Path,
+    /// Bounds added to the item's where clause for this impl only.
+    pub predicates: Vec<WherePredicate>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Args {
+            markers: Vec::new(),
+            strict: true,
+            introspect: false,
+            bounds: Bounds::None,
+            reason: None,
+            assertions: Vec::new(),
+            negative: Vec::new(),
+        };
+
+        while !input.is_empty() {
+            if input.parse::<Option<Token![!]>>()?.is_some() {
+                let name: syn::Ident = input.parse()?;
+                if !NEGATIVE_AUTO_TRAITS.iter().any(|auto| name == auto) {
+                    return Err(syn::Error::new_spanned(
+                        name,
+                        "only `Send`, `Sync` and `Unpin` can be opted out of",
+                    ));
+                }
+                args.negative.push(name);
+            } else if input.peek(syn::Ident)
+                && input.peek2(syn::Ident)
+                && input.fork().parse::<syn::Ident>()? == "assert"
+            {
+                input.parse::<syn::Ident>()?;
+                args.assertions
+                    .extend(Punctuated::<syn::Path, Token![+]>::parse_separated_nonempty(input)?);
+            } else if input.peek(syn::Ident)
+                && (input.peek2(Token![,])
+                    || input
+                        .cursor()
+                        .token_tree()
+                        .is_some_and(|(_, rest)| rest.eof()))
+                && input.fork().parse::<syn::Ident>()? == "introspect"
+            {
+                input.parse::<syn::Ident>()?;
+                args.introspect = true;
+            } else if input.peek(syn::Ident) && input.peek2(Token![=]) {
+                let option: syn::Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+                if option == "strict" {
+                    args.strict = input.parse::<syn::LitBool>()?.value;
+                } else if option == "bounds" {
+                    let mode: syn::LitStr = input.parse()?;
+                    args.bounds = match mode.value().as_str() {
+                        "params" => Bounds::Params,
+                        "fields" => Bounds::Fields,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                mode,
+                                "expected `\"params\"` or `\"fields\"`",
+                            ))
+                        }
+                    };
+                } else if option == "reason" {
+                    args.reason = Some(input.parse()?);
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        option,
+                        "expected trait name, `strict = bool`, `bounds = \"...\"` or `reason = \"...\"`",
+                    ));
+                }
+            } else {
+                args.markers.push(input.parse()?);
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        if args.introspect && !args.strict {
+            return Err(input.error("`introspect` requires strict mode"));
+        }
+
+        let unsafe_markers = args
+            .markers
+            .iter_mut()
+            .filter(|marker| marker.unsafety.is_some());
+        match &args.reason {
+            Some(reason) => {
+                let mut used = false;
+                for marker in unsafe_markers {
+                    marker.reason = Some(reason.clone());
+                    used = true;
+                }
+                if !used {
+                    return Err(syn::Error::new_spanned(
+                        reason,
+                        "`reason` is only used with `unsafe` markers",
+                    ));
+                }
+            }
+            None => {
+                if let Some(marker) = unsafe_markers.into_iter().next() {
+                    return Err(syn::Error::new_spanned(
+                        marker.unsafety,
+                        "`unsafe` markers require `reason = \"...\"` which justifies the implementation",
+                    ));
+                }
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+impl Parse for Marker {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let unsafety: Option<Token![unsafe]> = input.parse()?;
+        let path: syn::Path = input.parse()?;
+        let mut predicates = Vec::new();
+
+        let unsafe_auto_trait = path
+            .segments
+            .last()
+            .filter(|segment| segment.arguments.is_none())
+            .filter(|segment| UNSAFE_AUTO_TRAITS.iter().any(|name| segment.ident == name));
+        if let (None, Some(segment)) = (unsafety, unsafe_auto_trait) {
+            let name = &segment.ident;
+            return Err(syn::Error::new_spanned(
+                &path,
+                format!(
+                    "`{name}` can only be implemented with `unsafe {name}` and `reason = \"...\"`, \
+                     to check that the type is `{name}` use `assert {name}`"
+                ),
+            ));
+        }
+
+        if input.parse::<Option<Token![where]>>()?.is_some() {
+            loop {
+                predicates.push(input.parse()?);
+
+                // Comma either separates predicates or starts the next argument.
+                let fork = input.fork();
+                if fork.parse::<Option<Token![,]>>()?.is_none()
+                    || fork.parse::<WherePredicate>().is_err()
+                {
+                    break;
+                }
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Marker {
+            unsafety,
+            reason: None,
+            path,
+            predicates,
+        })
+    }
+}
+
+pub(crate) fn expand(args: Args, mut parsed_input: DeriveInput) -> syn::Result<TokenStream> {
+    let phantom = phantom_field(&args.negative, &mut parsed_input)?;
+
+    let ident = &parsed_input.ident;
+    let (_, ty_generics, _) = parsed_input.generics.split_for_impl();
+    let self_ty: syn::Type = parse_quote! { #ident #ty_generics };
+    let bounded_types = bounded_types(args.bounds, &parsed_input);
+
+    let mut gen = quote! { #parsed_input };
+    gen.extend(phantom);
+    gen.extend(assertions(&args.assertions, &parsed_input, &self_ty));
+    gen.extend(impls(
+        &himark_path(),
+        &args.markers,
+        if args.introspect {
+            Mode::Introspect
+        } else {
+            Mode::new(args.strict)
+        },
+        &parsed_input.generics,
+        &self_ty,
+        &bounded_types,
+        ident.span(),
+    ));
+
+    Ok(gen)
+}
+
+/// Implements `markers` for `self_ty`, `bounded_types` must implement each of them.
+///
+/// Shared by `#[mark]`, `denmark!` and helper macros of markers.
+pub(crate) fn impls(
+    himark: &TokenStream,
+    markers: &[Marker],
+    mode: Mode,
+    item_generics: &syn::Generics,
+    self_ty: &syn::Type,
+    bounded_types: &[syn::Type],
+    span: Span,
+) -> TokenStream {
+    let mut state = State {
+        self_ty: self_ty.clone(),
+        todo: Vec::new(),
+        done: Vec::new(),
+        markers: Vec::new(),
+        introspect: None,
+        registered: false,
+    };
+    if mode == Mode::Introspect {
+        let (impl_generics, _, where_clause) = item_generics.split_for_impl();
+        state.introspect = Some(Introspect {
+            impl_generics: impl_generics.to_token_stream(),
+            where_clause: where_clause.to_token_stream(),
+        });
+    }
+
+    let mut gen = TokenStream::new();
+    for marker in markers {
+        // Strict check names the trait as `dyn Trait`, which reports dyn compatibility errors for these.
+        if mode != Mode::Lenient && crate::marker::not_dyn_compatible(&marker.path).is_some() {
+            let message = format!("`{}` is not a marker trait", display_tokens(&marker.path));
+            gen.extend(syn::Error::new_spanned(&marker.path, message).into_compile_error());
+            continue;
+        }
+
+        let mut trait_name = marker.path.clone();
+        let mut generics = item_generics.clone();
+        let predicates: Vec<WherePredicate> = bounded_types
+            .iter()
+            .map(|ty| parse_quote! { #ty: #trait_name })
+            .chain(marker.predicates.iter().cloned())
+            .collect();
+        if !predicates.is_empty() {
+            move_bounds_to_where_clause(&mut generics);
+            generics.make_where_clause().predicates.extend(predicates);
+        }
+
+        // `Self` is not available outside of the impl block.
+        let mut replace_self = ReplaceSelf(self_ty);
+        replace_self.visit_path_mut(&mut trait_name);
+        replace_self.visit_generics_mut(&mut generics);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        let unsafety = &marker.unsafety;
+        let safety = marker.reason.as_ref().map(|reason| {
+            let doc = format!(" SAFETY: {}", reason.value());
+            quote! { #[doc = #doc] }
+        });
+        let item = quote_spanned! {span=>
+            #safety
+            #unsafety impl #impl_generics #trait_name for #self_ty #where_clause {}
+        };
+
+        // Looking up callbacks of auto traits can get import resolution stuck.
+        if mode == Mode::Lenient || callback::is_auto_trait(&trait_name) {
+            gen.extend(item);
+        } else {
+            state.todo.push(Entry {
+                path: trait_name.clone(),
+                impl_generics: impl_generics.to_token_stream(),
+                where_clause: where_clause.to_token_stream(),
+                item,
+            });
+        }
+        if mode != Mode::Lenient {
+            state.markers.push(trait_name.clone());
+            gen.extend(strict_check(himark, &trait_name, &generics, self_ty));
+            state.done.push(callback::key(&trait_name));
+        }
+    }
+
+    // Non-markers have no callback, the fallback drops their impls so only the strict check reports them.
+    gen.extend(callback::invoke(himark, &state));
+
+    gen
+}
+
+/// Types which must implement the trait for the item to be marked, according to `bounds` mode.
+fn bounded_types(bounds: Bounds, input: &DeriveInput) -> Vec<syn::Type> {
+    let generics = &input.generics;
+    match bounds {
+        Bounds::None => Vec::new(),
+        Bounds::Params => generics
+            .type_params()
+            .map(|param| {
+                let ident = &param.ident;
+                parse_quote! { #ident }
+            })
+            .collect(),
+        Bounds::Fields => {
+            let params: Vec<_> = generics
+                .type_params()
+                .map(|param| &param.ident)
+                .chain(generics.const_params().map(|param| &param.ident))
+                .collect();
+
+            let mut types: Vec<syn::Type> = Vec::new();
+            let mut seen = Vec::new();
+            for field in fields(input) {
+                let mut mentions = MentionsParams {
+                    params: &params,
+                    found: false,
+                };
+                mentions.visit_type(&field.ty);
+                let key = field.ty.to_token_stream().to_string();
+                if mentions.found && !seen.contains(&key) {
+                    seen.push(key);
+                    types.push(field.ty.clone());
+                }
+            }
+            types
+        }
+    }
+}
+
+/// Adds field which opts the struct out of `negative` auto traits.
+///
+/// Value of the field is available as `Struct::PHANTOM`. Unit structs are rejected,
+/// since making them braced would break their construction and patterns.
+fn phantom_field(negative: &[syn::Ident], input: &mut DeriveInput) -> syn::Result<TokenStream> {
+    let Some(first) = negative.first() else {
+        return Ok(TokenStream::new());
+    };
+    let syn::Data::Struct(data) = &mut input.data else {
+        return Err(syn::Error::new_spanned(
+            first,
+            "auto traits can only be opted out of by structs",
+        ));
+    };
+    let field_name = format_ident!("{}", PHANTOM_FIELD);
+    if data
+        .fields
+        .iter()
+        .any(|field| field.ident.as_ref() == Some(&field_name))
+    {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            format!("field `{PHANTOM_FIELD}` is reserved for opting out of auto traits"),
+        ));
+    }
+
+    let himark = himark_path();
+    let (types, values): (Vec<TokenStream>, Vec<TokenStream>) = NEGATIVE_AUTO_TRAITS
+        .iter()
+        .filter(|auto| negative.iter().any(|name| name == auto))
+        .map(|auto| {
+            let ty = format_ident!("Not{}", auto);
+            (quote! { #himark::#ty }, quote! { #himark::#ty::new() })
+        })
+        .unzip();
+    let (ty, value) = match (&types[..], &values[..]) {
+        ([ty], [value]) => (ty.clone(), value.clone()),
+        _ => (quote! { (#(#types),*) }, quote! { (#(#values),*) }),
+    };
+
+    let vis = &input.vis;
+    let ident = &input.ident;
+    match &mut data.fields {
+        syn::Fields::Named(fields) => {
+            fields.named.push(parse_quote! { #vis #field_name: #ty });
+        }
+        syn::Fields::Unnamed(fields) => {
+            fields.unnamed.push(parse_quote! { #vis #ty });
+        }
+        syn::Fields::Unit => {
+            return Err(syn::Error::new_spanned(
+                first,
+                format!(
+                    "unit structs cannot opt out of auto traits, \
+                     declare a braced struct instead, e.g. `struct {} {{}}`",
+                    ident
+                ),
+            ));
+        }
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Value of the field which opts the type out of auto traits.
+            #[allow(dead_code)]
+            #vis const PHANTOM: #ty = #value;
+        }
+    })
+}
+
+/// Fields of all variants of the item.
+fn fields(input: &DeriveInput) -> Vec<&syn::Field> {
+    match &input.data {
+        syn::Data::Struct(data) => data.fields.iter().collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|variant| variant.fields.iter())
+            .collect(),
+        syn::Data::Union(data) => data.fields.named.iter().collect(),
+    }
+}
+
+/// Static assertions of `#[mark(assert ...)]`, type parameters are assumed to implement asserted traits.
+///
+/// Auto traits are implemented by the item if all of its fields implement them,
+/// so they are asserted for each field, which makes errors point at the field responsible.
+/// Other traits are asserted for the item itself. Argument of the check provides implied bounds of the item.
+fn assertions(assertions: &[syn::Path], input: &DeriveInput, self_ty: &syn::Type) -> TokenStream {
+    if assertions.is_empty() {
+        return TokenStream::new();
+    }
+
+    let mut generics = input.generics.clone();
+    let params: Vec<_> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    if !params.is_empty() {
+        move_bounds_to_where_clause(&mut generics);
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(params.iter().map(|param| -> WherePredicate {
+                parse_quote! { #param: #(#assertions)+* }
+            }));
+    }
+    let mut replace_self = ReplaceSelf(self_ty);
+    replace_self.visit_generics_mut(&mut generics);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let checks = assertions.iter().map(|path| {
+        let mut path = path.clone();
+        replace_self.visit_path_mut(&mut path);
+
+        let types: Vec<syn::Type> = if callback::is_auto_trait(&path) {
+            fields(input)
+                .into_iter()
+                .map(|field| {
+                    let mut ty = field.ty.clone();
+                    replace_self.visit_type_mut(&mut ty);
+                    ty
+                })
+                .collect()
+        } else {
+            vec![self_ty.clone()]
+        };
+        let asserts = types.iter().map(|ty| {
+            quote_spanned! {ty.span()=>
+                assert::<#ty>();
+            }
+        });
+
+        quote_spanned! {path.span()=>
+            {
+                fn assert<T: ?Sized + #path>() {}
+                #(#asserts)*
+            }
+        }
+    });
+
+    quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            fn assertions #impl_generics (_: ::core::marker::PhantomData<#self_ty>) #where_clause {
+                #(#checks)*
+            }
+        };
+    }
+}
+
+/// Checks whether type refers to any of the generic parameters.
+struct MentionsParams<'a> {
+    params: &'a [&'a syn::Ident],
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for MentionsParams<'_> {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.leading_colon.is_none()
+            && path
+                .segments
+                .first()
+                .is_some_and(|segment| self.params.contains(&&segment.ident))
+        {
+            self.found = true;
+        }
+        visit::visit_path(self, path);
+    }
+}
+
+/// Moves inline bounds of generic parameters into the where clause.
+///
+/// Keeps generated impl from declaring bounds for the same parameter in two places.
+pub(crate) fn move_bounds_to_where_clause(generics: &mut syn::Generics) {
+    let mut predicates = Vec::<WherePredicate>::new();
+    for param in generics.params.iter_mut() {
+        match param {
+            syn::GenericParam::Type(param) if !param.bounds.is_empty() => {
+                let ident = &param.ident;
+                let bounds = std::mem::take(&mut param.bounds);
+                param.colon_token = None;
+                predicates.push(parse_quote! { #ident: #bounds });
+            }
+            syn::GenericParam::Lifetime(param) if !param.bounds.is_empty() => {
+                let lifetime = &param.lifetime;
+                let bounds = std::mem::take(&mut param.bounds);
+                param.colon_token = None;
+                predicates.push(parse_quote! { #lifetime: #bounds });
+            }
+            _ => {}
+        }
+    }
+    let where_clause = generics.make_where_clause();
+    let existing = std::mem::take(&mut where_clause.predicates);
+    where_clause.predicates.extend(predicates);
+    where_clause.predicates.extend(existing);
+}
+
+/// Replaces `Self` with the type of the marked item.
+pub(crate) struct ReplaceSelf<'a>(pub &'a syn::Type);
+
+impl VisitMut for ReplaceSelf<'_> {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        match ty {
+            syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self") => {
+                *ty = self.0.clone();
+            }
+            _ => visit_mut::visit_type_mut(self, ty),
+        }
+    }
+}
+
+/// Emits compile-time check that `trait_name` was validated with `#[marker]`.
+///
+/// Local trait lets us report error message which names the trait as it was written by the user.
+/// Check is performed inside of a function generic over the item's parameters
+/// since trait arguments may refer to them, its argument provides implied bounds of the item.
+fn strict_check(
+    himark: &TokenStream,
+    trait_name: &syn::Path,
+    generics: &syn::Generics,
+    self_ty: &syn::Type,
+) -> TokenStream {
+    let message = format!(
+        "`{}` is not a marker trait",
+        display_tokens(trait_name)
+            .replace('{', "{{")
+            .replace('}', "}}")
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let assertion = quote_spanned! {trait_name.span()=>
+        assert_marker::<dyn #trait_name>();
+    };
+
+    quote! {
+        const _: () = {
+            #[diagnostic::on_unimplemented(
+                message = #message,
+                label = "not a marker trait",
+                note = "annotate the trait with `#[himark::marker]` or pass `strict = false` to `#[mark]`"
+            )]
+            trait Marker {}
+
+            impl<T: ?Sized + #himark::__private::Marker> Marker for T {}
+
+            fn assert_marker<T: ?Sized + Marker>() {}
+
+            #[allow(dead_code)]
+            fn check #impl_generics (_: ::core::marker::PhantomData<#self_ty>) #where_clause {
+                #assertion
+            }
+        };
+    }
+}
+
+/// Renders tokens the way user would write them, i.e. without spaces around punctuation.
+pub(crate) fn display_tokens(tokens: impl ToTokens) -> String {
+    let rendered = tokens.to_token_stream().to_string();
+    let chars: Vec<char> = rendered.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| {
+            c != ' '
+                || (i > 0 && i + 1 < chars.len() && is_word(chars[i - 1]) && is_word(chars[i + 1]))
+        })
+        .map(|(_, &c)| c)
+        .collect()
+}
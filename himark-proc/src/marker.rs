@@ -0,0 +1,783 @@
+//! Implementation of the `#[marker]` attribute.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{parse_quote, ItemTrait, Token, TraitBound, TypeParamBound, Visibility, WherePredicate};
+
+use crate::himark_path;
+use crate::mark::display_tokens;
+
+/// Arguments of the `#[marker(...)]` attribute.
+#[derive(Default)]
+pub(crate) struct Args {
+    /// Marker can only be implemented inside of the defining crate.
+    pub sealed: bool,
+    /// `#[mark]` implements marker supertraits together with the marker.
+    pub implies: bool,
+    /// Wrappers which implement the marker whenever the wrapped type does.
+    pub propagate: Vec<Wrapper>,
+    /// Markers which cannot be implemented together with the marker.
+    pub excludes: Vec<syn::Path>,
+    /// Error message reported for types which aren't marked.
+    pub message: Option<syn::LitStr>,
+    /// Label of the unmarked type in the error.
+    pub label: Option<syn::LitStr>,
+    /// Note attached to the error.
+    pub note: Option<syn::LitStr>,
+    /// Name of the helper macro which implements the marker for a list of types.
+    pub helper: Option<syn::Ident>,
+    /// Path of the module which defines the marker, used by the helper macro.
+    pub module: Option<syn::Path>,
+    /// `#[mark]` registers marked types, which are listed by `<dyn Trait>::implementors()`.
+    pub registry: bool,
+}
+
+/// Std wrapper type for `#[marker(propagate(...))]`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Wrapper {
+    Ref,
+    RefMut,
+    Box,
+    Rc,
+    Arc,
+    Option,
+    Phantom,
+    ManuallyDrop,
+    Slice,
+    Array,
+}
+
+impl Wrapper {
+    /// Wrappers named by `propagate` argument, including presets.
+    fn named(name: &str) -> Option<&'static [Wrapper]> {
+        use Wrapper::*;
+        Some(match name {
+            "ref" => &[Ref],
+            "ref_mut" => &[RefMut],
+            "box" => &[Box],
+            "rc" => &[Rc],
+            "arc" => &[Arc],
+            "option" => &[Option],
+            "phantom" => &[Phantom],
+            "manually_drop" => &[ManuallyDrop],
+            "slice" => &[Slice],
+            "array" => &[Array],
+            "all_refs" => &[Ref, RefMut],
+            "all_smart_pointers" => &[Box, Rc, Arc],
+            "all" => &[
+                Ref,
+                RefMut,
+                Box,
+                Rc,
+                Arc,
+                Option,
+                Phantom,
+                ManuallyDrop,
+                Slice,
+                Array,
+            ],
+            _ => return None,
+        })
+    }
+
+    /// Wrapper applied to `ty` and generic parameter it introduces, if any.
+    fn wrap(self, ty: &syn::Ident) -> (Option<syn::GenericParam>, syn::Type) {
+        let lifetime: syn::Lifetime = parse_quote! { '__himark };
+        let len = format_ident!("__HIMARK_N");
+        match self {
+            Wrapper::Ref => (
+                Some(parse_quote! { #lifetime }),
+                parse_quote! { &#lifetime #ty },
+            ),
+            Wrapper::RefMut => (
+                Some(parse_quote! { #lifetime }),
+                parse_quote! { &#lifetime mut #ty },
+            ),
+            Wrapper::Box => (None, parse_quote! { ::std::boxed::Box<#ty> }),
+            Wrapper::Rc => (None, parse_quote! { ::std::rc::Rc<#ty> }),
+            Wrapper::Arc => (None, parse_quote! { ::std::sync::Arc<#ty> }),
+            Wrapper::Option => (None, parse_quote! { ::core::option::Option<#ty> }),
+            Wrapper::Phantom => (None, parse_quote! { ::core::marker::PhantomData<#ty> }),
+            Wrapper::ManuallyDrop => (None, parse_quote! { ::core::mem::ManuallyDrop<#ty> }),
+            Wrapper::Slice => (None, parse_quote! { [#ty] }),
+            Wrapper::Array => (
+                Some(parse_quote! { const #len: usize }),
+                parse_quote! { [#ty; #len] },
+            ),
+        }
+    }
+
+    /// Whether other crates can implement traits for the wrapper of their types.
+    fn is_fundamental(self) -> bool {
+        matches!(self, Wrapper::Ref | Wrapper::RefMut | Wrapper::Box)
+    }
+
+    /// Whether wrapper accepts unsized types.
+    fn accepts_unsized(self) -> bool {
+        !matches!(
+            self,
+            Wrapper::Option | Wrapper::ManuallyDrop | Wrapper::Slice | Wrapper::Array
+        )
+    }
+}
+
+impl Args {
+    pub fn parser(&mut self) -> impl syn::parse::Parser<Output = ()> + '_ {
+        syn::meta::parser(move |meta| {
+            if meta.path.is_ident("sealed") {
+                self.sealed = true;
+                Ok(())
+            } else if meta.path.is_ident("implies") {
+                self.implies = true;
+                Ok(())
+            } else if meta.path.is_ident("registry") {
+                self.registry = true;
+                Ok(())
+            } else if meta.path.is_ident("propagate") {
+                meta.parse_nested_meta(|wrapper| {
+                    let name = wrapper.path.get_ident().map(ToString::to_string);
+                    match name.as_deref().and_then(Wrapper::named) {
+                        Some(wrappers) => {
+                            for &wrapper in wrappers {
+                                if !self.propagate.contains(&wrapper) {
+                                    self.propagate.push(wrapper);
+                                }
+                            }
+                            Ok(())
+                        }
+                        None => Err(wrapper.error(
+                            "unsupported wrapper, expected one of `ref`, `ref_mut`, `box`, `rc`, `arc`, \
+                             `option`, `phantom`, `manually_drop`, `slice`, `array`, \
+                             `all_refs`, `all_smart_pointers` or `all`",
+                        )),
+                    }
+                })
+            } else if meta.path.is_ident("excludes") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                self.excludes
+                    .extend(Punctuated::<syn::Path, Token![,]>::parse_terminated(
+                        &content,
+                    )?);
+                Ok(())
+            } else if meta.path.is_ident("message") {
+                self.message = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("label") {
+                self.label = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("note") {
+                self.note = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("macro") {
+                self.helper = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("module") {
+                self.module = Some(parse_module(&meta)?);
+                Ok(())
+            } else {
+                Err(meta.error(
+                    "unsupported marker argument, expected `sealed`, `implies`, `propagate`, `excludes`, \
+                     `message`, `label`, `note`, `macro`, `module` or `registry`",
+                ))
+            }
+        })
+    }
+}
+
+/// Renders trait name with its parameters as `on_unimplemented` format string, e.g. `Storage<{T}>`.
+fn format_name(input_trait: &ItemTrait) -> String {
+    let ident = &input_trait.ident;
+    if input_trait.generics.params.is_empty() {
+        return ident.to_string();
+    }
+    let params: Vec<_> = input_trait
+        .generics
+        .params
+        .iter()
+        .map(|param| match param {
+            syn::GenericParam::Lifetime(param) => param.lifetime.to_string(),
+            syn::GenericParam::Type(param) => format!("{{{}}}", param.ident),
+            syn::GenericParam::Const(param) => format!("{{{}}}", param.ident),
+        })
+        .collect();
+    format!("{}<{}>", ident, params.join(", "))
+}
+
+/// `on_unimplemented` attribute which suggests marking the type, unless the trait already declares one.
+fn on_unimplemented(input_trait: &ItemTrait, args: &Args) -> Option<syn::Attribute> {
+    let declared = input_trait.attrs.iter().any(|attr| {
+        let path = attr.path();
+        path.segments.len() == 2
+            && path.segments[0].ident == "diagnostic"
+            && path.segments[1].ident == "on_unimplemented"
+    });
+    if declared {
+        return None;
+    }
+
+    let name = format_name(input_trait);
+    let span = input_trait.ident.span();
+    let string = |arg: &Option<syn::LitStr>, default: String| {
+        arg.clone()
+            .unwrap_or_else(|| syn::LitStr::new(&default, span))
+    };
+    let message = string(
+        &args.message,
+        format!("`{{Self}}` is not marked with `{name}`"),
+    );
+    let label = string(&args.label, format!("not marked with `{name}`"));
+    let note = string(
+        &args.note,
+        format!(
+            "annotate the type with `#[himark::mark({})]` or use `himark::denmark!` for foreign types",
+            input_trait.ident
+        ),
+    );
+
+    Some(parse_quote! {
+        #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+    })
+}
+
+/// First paragraph of the documentation of the trait, with lines joined by spaces.
+fn doc_summary(input_trait: &ItemTrait) -> String {
+    let docs: Vec<String> = input_trait
+        .attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) if path.is_ident("doc") => Some(doc.value()),
+            _ => None,
+        })
+        .collect();
+
+    docs.iter()
+        .flat_map(|doc| doc.split('\n'))
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Checks whether where clause predicate constrains `Self`, i.e. declares a supertrait.
+fn is_self_predicate(predicate: &WherePredicate) -> bool {
+    match predicate {
+        WherePredicate::Type(predicate) => {
+            matches!(&predicate.bounded_ty, syn::Type::Path(ty) if ty.qself.is_none() && ty.path.is_ident("Self"))
+        }
+        _ => false,
+    }
+}
+
+/// Std traits which aren't dyn compatible, mostly because they require `Self: Sized`.
+///
+/// Checks of `#[marker]` and `#[mark]` name traits as `dyn Trait`, which fails with dyn compatibility errors for these.
+const NOT_DYN_COMPATIBLE: [&str; 9] = [
+    "Sized",
+    "Clone",
+    "Copy",
+    "Default",
+    "PartialEq",
+    "Eq",
+    "PartialOrd",
+    "Ord",
+    "Hash",
+];
+
+/// Name of the trait if it's one of [`NOT_DYN_COMPATIBLE`] std traits.
+pub(crate) fn not_dyn_compatible(path: &syn::Path) -> Option<&syn::Ident> {
+    let name = &path.segments.last()?.ident;
+    NOT_DYN_COMPATIBLE
+        .iter()
+        .any(|std| name == std)
+        .then_some(name)
+}
+
+/// Trait bounds on `Self`, both inline and from the where clause.
+fn supertraits(input_trait: &ItemTrait) -> Vec<&TraitBound> {
+    let where_supertraits = input_trait
+        .generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter())
+        .filter(|predicate| is_self_predicate(predicate))
+        .flat_map(|predicate| match predicate {
+            WherePredicate::Type(predicate) => predicate.bounds.iter(),
+            _ => unreachable!(),
+        });
+
+    input_trait
+        .supertraits
+        .iter()
+        .chain(where_supertraits)
+        .filter_map(|bound| match bound {
+            TypeParamBound::Trait(bound) => Some(bound),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Unique name for the callback macro which `#[mark]` invokes for the marker.
+///
+/// Exported macros share crate root namespace, hence the hash of the definition site.
+/// Debug output of the span gives its position in the crate, and its hygiene context
+/// for idents which come from macros, e.g. markers declared by separate `macro_rules!` invocations.
+/// Items repeated by a single invocation share both, only their `module = crate::...` differs.
+pub(crate) fn callback_name(ident: &syn::Ident, module: Option<&syn::Path>) -> syn::Ident {
+    let mut hasher = DefaultHasher::new();
+    ident.to_string().hash(&mut hasher);
+    format!("{:?}", ident.span()).hash(&mut hasher);
+    module.map(display_tokens).hash(&mut hasher);
+    format_ident!("__himark_{}_{:016x}", ident, hasher.finish())
+}
+
+/// Supertraits implied by the marker as they should be written in its callback.
+///
+/// Callback expands in the crate of the marked item, `crate::` paths are rewritten to `$crate::`.
+/// Other paths resolve at the mark site.
+fn implied_supertraits(supertraits: &[&TraitBound]) -> syn::Result<Vec<TokenStream>> {
+    supertraits
+        .iter()
+        .map(|bound| {
+            let path = &bound.path;
+            let first = path.segments.first().map(|segment| &segment.ident);
+            match first {
+                Some(ident) if path.leading_colon.is_none() && ident == "crate" => {
+                    let rest = path.segments.iter().skip(1);
+                    Ok(quote! { $crate #(:: #rest)* })
+                }
+                Some(ident) if path.leading_colon.is_none() && (ident == "self" || ident == "super") => {
+                    Err(syn::Error::new_spanned(
+                        path,
+                        "implied supertraits must be named with a plain, `crate::` or absolute path",
+                    ))
+                }
+                _ => Ok(quote! { #path }),
+            }
+        })
+        .collect()
+}
+
+/// Callback macro which `#[mark]` invokes with the state of its expansion.
+///
+/// It passes the state back to `chain!` together with information about the marker, see [`crate::callback`].
+/// It shares the name with the trait so it gets imported together with it.
+/// Callbacks of sealed markers are exported as well, so that marking in other crates fails on the sealing trait.
+fn callback(input_trait: &ItemTrait, args: &Args, implies: &[TokenStream]) -> TokenStream {
+    let ident = &input_trait.ident;
+    let name = callback_name(ident, args.module.as_ref());
+    // Module of the sealing token, which is private to the crate.
+    let seal = args.sealed.then(|| {
+        let module_from_root = from_crate_root(&module_or_root(args.module.as_ref()));
+        let token = format_ident!("__himark_{}", ident);
+        quote! { #module_from_root::#token }
+    });
+    let exclusive = !args.excludes.is_empty();
+    let registry = args.registry;
+    let params = &input_trait.generics.params;
+
+    let (export, vis) = macro_visibility(&input_trait.vis, false);
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        #export
+        macro_rules! #name {
+            ([$($himark:tt)*] [$($state:tt)*]) => {
+                $($himark)*::__private::chain! {
+                    [$($himark)*]
+                    [$($state)*]
+                    [#seal]
+                    [#exclusive]
+                    [#registry]
+                    [<#params>]
+                    [#(#implies)+*]
+                }
+            };
+        }
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        #vis use #name as #ident;
+    }
+}
+
+/// Parses `module = crate::...` argument of `#[marker]` and `#[reify]`.
+pub(crate) fn parse_module(meta: &ParseNestedMeta) -> syn::Result<syn::Path> {
+    let module: syn::Path = meta.value()?.parse()?;
+    if module
+        .segments
+        .first()
+        .is_some_and(|segment| segment.ident == "crate")
+    {
+        Ok(module)
+    } else {
+        Err(syn::Error::new_spanned(
+            module,
+            "module must be a `crate::` path",
+        ))
+    }
+}
+
+/// Module given by `module = crate::...`, the crate root by default.
+pub(crate) fn module_or_root(module: Option<&syn::Path>) -> syn::Path {
+    module.cloned().unwrap_or_else(|| parse_quote! { crate })
+}
+
+/// `#[macro_export]` attribute and visibility of the import of a macro generated for an item.
+///
+/// Macros of public items are exported, except for macros of sealed markers which stay in the crate.
+pub(crate) fn macro_visibility(vis: &Visibility, sealed: bool) -> (TokenStream, Visibility) {
+    match vis {
+        Visibility::Public(_) if !sealed => (quote! { #[macro_export] }, vis.clone()),
+        Visibility::Public(_) => (quote! {}, parse_quote! { pub(crate) }),
+        vis => (quote! {}, vis.clone()),
+    }
+}
+
+/// `crate::` path of a module as seen from an exported macro, i.e. starting with `$crate`.
+pub(crate) fn from_crate_root(module: &syn::Path) -> TokenStream {
+    let rest = module.segments.iter().skip(1);
+    quote! { $crate #(:: #rest)* }
+}
+
+/// Helper macro generated by `#[marker(macro = name)]`, which implements the marker for a list of types.
+///
+/// Helper expands in other modules and crates, so it names the marker and `himark` through paths from `$crate`.
+/// `himark` is re-exported next to the marker since the crate using the helper may not depend on it.
+/// Unlike the callback it's not exported for sealed markers.
+fn helper(input_trait: &ItemTrait, args: &Args, himark: &TokenStream) -> TokenStream {
+    let Some(helper) = &args.helper else {
+        return quote! {};
+    };
+    let ident = &input_trait.ident;
+    let name = callback_name(&format_ident!("macro_{}", helper), args.module.as_ref());
+    let reexport = format_ident!("__himark_crate_{}", ident);
+
+    let module_from_root = from_crate_root(&module_or_root(args.module.as_ref()));
+    let (export, vis) = macro_visibility(&input_trait.vis, args.sealed);
+
+    quote! {
+        #[doc(hidden)]
+        #vis use #himark as #reexport;
+
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        #export
+        macro_rules! #name {
+            ($($input:tt)*) => {
+                #module_from_root::#reexport::__private::apply! {
+                    [#module_from_root::#reexport]
+                    [#module_from_root::#ident]
+                    $($input)*
+                }
+            };
+        }
+
+        #[allow(unused_imports)]
+        #vis use #name as #helper;
+    }
+}
+
+pub(crate) fn expand(args: Args, mut input_trait: ItemTrait) -> syn::Result<TokenStream> {
+    if !input_trait.items.is_empty() {
+        return Err(syn::Error::new_spanned(
+            input_trait,
+            "The #[marker] attribute can only be applied to empty traits",
+        ));
+    }
+
+    let supertraits = supertraits(&input_trait);
+
+    if let Some((bound, name)) = supertraits
+        .iter()
+        .find_map(|bound| Some((bound, not_dyn_compatible(&bound.path)?)))
+    {
+        return Err(syn::Error::new_spanned(
+            bound,
+            format!(
+                "`{name}` is not a marker trait, markers cannot require traits which aren't dyn compatible"
+            ),
+        ));
+    }
+
+    if let Some(helper) = &args.helper {
+        if !input_trait.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                helper,
+                "helper macros can't be generated for generic markers",
+            ));
+        }
+        if input_trait.unsafety.is_some() {
+            return Err(syn::Error::new_spanned(
+                helper,
+                "helper macros can't be generated for unsafe markers",
+            ));
+        }
+    } else if let (Some(module), false) = (&args.module, args.sealed) {
+        return Err(syn::Error::new_spanned(
+            module,
+            "`module` is only used with `macro = name` or `sealed`",
+        ));
+    }
+
+    if let Some(excluded) = args.excludes.first() {
+        if !input_trait.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input_trait.generics,
+                "exclusive markers cannot be generic",
+            ));
+        }
+        if args
+            .propagate
+            .iter()
+            .any(|wrapper| wrapper.is_fundamental())
+        {
+            return Err(syn::Error::new_spanned(
+                excluded,
+                "exclusive markers cannot propagate through `ref`, `ref_mut` or `box`, \
+                 other crates could implement the excluded marker for them",
+            ));
+        }
+    }
+
+    if args.registry && !input_trait.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input_trait.generics,
+            "registry can't be used with generic markers",
+        ));
+    }
+
+    let implies = if args.implies {
+        implied_supertraits(&supertraits)?
+    } else {
+        Vec::new()
+    };
+
+    // `Self` predicates are meaningless outside of the trait definition.
+    let mut generics = input_trait.generics.clone();
+    if let Some(where_clause) = &mut generics.where_clause {
+        where_clause.predicates = where_clause
+            .predicates
+            .iter()
+            .filter(|predicate| !is_self_predicate(predicate))
+            .cloned()
+            .collect();
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let himark = himark_path();
+    let ident = input_trait.ident.clone();
+    let name = ident.to_string();
+
+    let supertrait_infos = supertraits.iter().map(|bound| {
+        quote! { <dyn #bound as #himark::MarkerMeta>::INFO }
+    });
+    let supertrait_infos = quote! { #(#supertrait_infos),* };
+
+    let assertions = supertraits.iter().map(|bound| {
+        quote_spanned! {bound.span()=>
+            #himark::__private::assert_marker::<dyn #bound>();
+        }
+    });
+
+    let assert_supertraits = quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            fn assert_supertraits #impl_generics () #where_clause {
+                #(#assertions)*
+            }
+        };
+    };
+
+    // Token type identifying the marker, private to the crate.
+    // Its module is public, so glob imports of other crates can reach it, but don't import the token.
+    let module = format_ident!("__himark_{}", ident);
+    let token = if args.sealed || !args.excludes.is_empty() {
+        input_trait
+            .attrs
+            .push(parse_quote! { #[allow(private_bounds)] });
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            pub mod #module {
+                pub(crate) struct __HimarkToken;
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    if args.sealed {
+        input_trait.colon_token.get_or_insert_with(Default::default);
+        input_trait
+            .supertraits
+            .push(parse_quote! { #himark::__private::Sealed<#module::__HimarkToken> });
+    }
+
+    // Callback of a sealed marker and helper macro are unusable if the marker can't be found in the module.
+    let check_module = if args.sealed || args.helper.is_some() {
+        let module = module_or_root(args.module.as_ref());
+        quote_spanned! {module.span()=>
+            #[allow(unused_imports)]
+            use #module::#ident as _;
+        }
+    } else {
+        quote! {}
+    };
+
+    // Impls of `Excludes` for types marked with excluded markers overlap with impls made by `#[mark]`.
+    let exclusion = if args.excludes.is_empty() {
+        quote! {}
+    } else {
+        input_trait.colon_token.get_or_insert_with(Default::default);
+        input_trait
+            .supertraits
+            .push(parse_quote! { #himark::__private::Exclusive<#module::__HimarkToken> });
+
+        let excludes = args.excludes.iter().map(|excluded| {
+            quote_spanned! {excluded.span()=>
+                impl<__HimarkT: ?Sized + #excluded> #himark::__private::Excludes<__HimarkT, dyn #excluded> for dyn #ident {}
+            }
+        });
+
+        quote! {
+            impl #himark::__private::Token for #module::__HimarkToken {
+                type Marker = dyn #ident;
+            }
+
+            #(#excludes)*
+        }
+    };
+
+    // Sealing token is private, hence it has to be implemented here instead of through `#[mark]`.
+    let unsafety = &input_trait.unsafety;
+    let propagation = args.propagate.iter().map(|wrapper| {
+        let param = format_ident!("__HimarkT");
+        let (wrapper_param, wrapped) = wrapper.wrap(&param);
+        let mut wrapper_generics = generics.clone();
+        match wrapper_param {
+            Some(lifetime @ syn::GenericParam::Lifetime(_)) => {
+                wrapper_generics.params.insert(0, lifetime)
+            }
+            Some(param) => wrapper_generics.params.push(param),
+            None => {}
+        }
+        wrapper_generics.params.push(if wrapper.accepts_unsized() {
+            parse_quote! { #param: ?Sized + #ident #ty_generics }
+        } else {
+            parse_quote! { #param: #ident #ty_generics }
+        });
+        let (impl_generics, _, where_clause) = wrapper_generics.split_for_impl();
+
+        let seal = args.sealed.then(|| {
+            quote! {
+                impl #impl_generics #himark::__private::Sealed<#module::__HimarkToken> for #wrapped #where_clause {}
+            }
+        });
+        let exclude = (!args.excludes.is_empty()).then(|| {
+            let mut exclude_generics = wrapper_generics.clone();
+            exclude_generics.params.push(parse_quote! { __HimarkO: ?Sized });
+            let (impl_generics, _, where_clause) = exclude_generics.split_for_impl();
+            quote! {
+                impl #impl_generics #himark::__private::Excludes<#wrapped, __HimarkO> for dyn #ident #where_clause {}
+            }
+        });
+        quote! {
+            #unsafety impl #impl_generics #ident #ty_generics for #wrapped #where_clause {}
+            #seal
+            #exclude
+        }
+    });
+    let propagation = quote! { #(#propagation)* };
+
+    let registry = if args.registry {
+        let vis = &input_trait.vis;
+        let doc = format!(
+            " Types marked with `{}` by `#[mark]` or `denmark!` in every crate linked into the binary.",
+            ident
+        );
+        quote! {
+            #himark::__private::registry! {
+                #[allow(dead_code)]
+                impl dyn #ident {
+                    #[doc = #doc]
+                    ///
+                    /// Generic types and types marked with `strict = false` are not registered.
+                    #vis fn implementors() -> impl ::core::iter::Iterator<Item = &'static #himark::TypeEntry> {
+                        #himark::__private::implementors(
+                            <dyn #ident as #himark::MarkerMeta>::INFO,
+                        )
+                    }
+
+                    /// Checks whether the type is registered with the marker, e.g. for `<dyn Any>::type_id`.
+                    #vis fn is_implemented_by(type_id: ::core::any::TypeId) -> bool {
+                        Self::implementors().any(|entry| entry.type_id() == type_id)
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let doc = doc_summary(&input_trait);
+    let sealed = args.sealed;
+    let exclusive = !args.excludes.is_empty();
+    let implies_supertraits = args.implies;
+    let is_unsafe = input_trait.unsafety.is_some();
+
+    let callback = callback(&input_trait, &args, &implies);
+    let helper = helper(&input_trait, &args, &himark);
+
+    if let Some(attr) = on_unimplemented(&input_trait, &args) {
+        input_trait.attrs.push(attr);
+    }
+
+    Ok(quote! {
+        #input_trait
+
+        impl #impl_generics #himark::__private::Marker for dyn #ident #ty_generics + '_ #where_clause {}
+
+        impl #impl_generics #himark::MarkerMeta for dyn #ident #ty_generics + '_ #where_clause {
+            const INFO: #himark::MarkerInfo = #himark::MarkerInfo::new(#name, ::core::module_path!());
+            const DOC: &'static str = #doc;
+            const SUPERTRAITS: &'static [#himark::MarkerInfo] = &[#supertrait_infos];
+            const SEALED: bool = #sealed;
+            const EXCLUSIVE: bool = #exclusive;
+            const IMPLIES: bool = #implies_supertraits;
+            const UNSAFE: bool = #is_unsafe;
+        }
+
+        #assert_supertraits
+
+        #token
+
+        #check_module
+
+        #exclusion
+
+        #propagation
+
+        #registry
+
+        #callback
+
+        #helper
+    })
+}
@@ -0,0 +1,258 @@
+//! Implementation of the `#[reify]` attribute and the `dispatch!` macro.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::Token;
+
+use crate::family::derives;
+use crate::himark_path;
+use crate::marker::{
+    callback_name, from_crate_root, macro_visibility, module_or_root, parse_module,
+};
+
+/// Arguments of the `#[reify(...)]` attribute.
+#[derive(Default)]
+pub(crate) struct Args {
+    /// Path of the module which defines the enum, used by `dispatch!`.
+    pub module: Option<syn::Path>,
+}
+
+/// Input of the `dispatch!` macro, e.g. `format, |F: FormatMarker| parse::<F>(input)`.
+pub(crate) struct Dispatch {
+    value: syn::Expr,
+    param: syn::Ident,
+    marker: syn::Path,
+    body: syn::Expr,
+}
+
+impl Args {
+    pub fn parser(&mut self) -> impl syn::parse::Parser<Output = ()> + '_ {
+        syn::meta::parser(move |meta| {
+            if meta.path.is_ident("module") {
+                self.module = Some(parse_module(&meta)?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported reify argument, expected `module`"))
+            }
+        })
+    }
+}
+
+impl Parse for Dispatch {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value = input.parse()?;
+        input.parse::<Token![,]>()?;
+        input.parse::<Token![|]>()?;
+        let param = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let marker = input.parse()?;
+        input.parse::<Token![|]>()?;
+        let body = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Dispatch {
+            value,
+            param,
+            marker,
+            body,
+        })
+    }
+}
+
+pub(crate) fn expand(args: Args, input_enum: syn::ItemEnum) -> syn::Result<TokenStream> {
+    if !input_enum.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input_enum.generics,
+            "reified enums cannot be generic",
+        ));
+    }
+    if let Some(variant) = input_enum
+        .variants
+        .iter()
+        .find(|variant| !matches!(variant.fields, syn::Fields::Unit))
+    {
+        return Err(syn::Error::new_spanned(
+            &variant.fields,
+            "reified enums can only have unit variants",
+        ));
+    }
+
+    let himark = himark_path();
+    let vis = &input_enum.vis;
+    let ident = &input_enum.ident;
+    let marker = format_ident!("{}Marker", ident);
+    let token = format_ident!("__himark_{}", marker);
+    let types_module = types_module(ident);
+
+    let derives = derives();
+    let types = input_enum.variants.iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        let docs = variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"));
+        let fallback = format!(
+            " Type-level [`{0}::{1}`](super::{0}::{1}).",
+            ident, variant_ident
+        );
+        let doc = if variant.attrs.iter().any(|attr| attr.path().is_ident("doc")) {
+            quote! { #(#docs)* }
+        } else {
+            quote! { #[doc = #fallback] }
+        };
+        quote! {
+            #doc
+            #derives
+            pub struct #variant_ident;
+
+            impl #himark::__private::Sealed<super::#token::#marker> for #variant_ident {}
+
+            impl super::#marker for #variant_ident {
+                const VALUE: super::#ident = super::#ident::#variant_ident;
+            }
+        }
+    });
+
+    let dispatch = dispatch(&args, &input_enum, &marker, &types_module);
+    let doc = format!(
+        " Type-level variants of [`{}`], implemented by a zero-sized type for each of them in [`{}`].",
+        ident, types_module
+    );
+    let module_doc = format!(
+        " Zero-sized types of the variants of [`{0}`](super::{0}), see [`{1}`](super::{1}).",
+        ident, marker
+    );
+
+    Ok(quote! {
+        #input_enum
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        mod #token {
+            pub struct #marker;
+        }
+
+        #[doc = #doc]
+        ///
+        /// Use `himark::dispatch!` to turn the runtime value into one of the types.
+        #vis trait #marker: #himark::__private::Sealed<#token::#marker> + 'static {
+            const VALUE: #ident;
+        }
+
+        #[doc = #module_doc]
+        #vis mod #types_module {
+            #(#types)*
+        }
+
+        #dispatch
+    })
+}
+
+/// Module of the variant types, the enum name in snake case, e.g. `format` for `Format`.
+///
+/// Keywords get a trailing underscore, e.g. `type_` for `Type`.
+fn types_module(ident: &syn::Ident) -> syn::Ident {
+    let mut name = String::new();
+    let mut previous_lower = false;
+    for c in ident.to_string().chars() {
+        if c.is_uppercase() && previous_lower {
+            name.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        name.extend(c.to_lowercase());
+    }
+    if syn::parse_str::<syn::Ident>(&name).is_err() {
+        name.push('_');
+    }
+    syn::Ident::new(&name, ident.span())
+}
+
+/// Macro invoked by `dispatch!`, which shares the name with the marker so it gets imported together with it.
+///
+/// Like helper macros of markers it names the enum and its types through paths from `$crate`.
+fn dispatch(
+    args: &Args,
+    input_enum: &syn::ItemEnum,
+    marker: &syn::Ident,
+    types_module: &syn::Ident,
+) -> TokenStream {
+    let ident = &input_enum.ident;
+    let name = callback_name(&format_ident!("dispatch_{}", marker), args.module.as_ref());
+
+    let module_from_root = from_crate_root(&module_or_root(args.module.as_ref()));
+    let (export, vis) = macro_visibility(&input_enum.vis, false);
+
+    // `dispatch!` can't find the types if the enum can't be found in the given module.
+    let check_module = args.module.as_ref().map(|module| {
+        quote_spanned! {module.span()=>
+            const _: ::core::marker::PhantomData<#ident> = ::core::marker::PhantomData::<#module::#ident>;
+        }
+    });
+
+    let arms = input_enum.variants.iter().map(|variant| {
+        let variant = &variant.ident;
+        quote! {
+            #module_from_root::#ident::#variant => {
+                type $param = #module_from_root::#types_module::#variant;
+                $body
+            }
+        }
+    });
+
+    quote! {
+        #check_module
+
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        #export
+        macro_rules! #name {
+            ($value:expr, $param:ident, $body:expr) => {
+                match $value {
+                    #(#arms)*
+                }
+            };
+        }
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        #vis use #name as #marker;
+    }
+}
+
+/// Imports the macro generated by `#[reify]` together with the marker and invokes it.
+///
+/// Falls back to an error for traits which aren't generated by `#[reify]`, like `invoke` of [`crate::callback`].
+/// Plain names of such traits can get import resolution stuck instead, which is an error as well.
+pub(crate) fn expand_dispatch(input: Dispatch) -> syn::Result<TokenStream> {
+    let Dispatch {
+        value,
+        param,
+        marker,
+        body,
+    } = input;
+    let himark = himark_path();
+
+    if let Some(segment) = marker
+        .segments
+        .iter()
+        .find(|segment| !segment.arguments.is_none())
+    {
+        return Err(syn::Error::new_spanned(
+            &segment.arguments,
+            "reified markers have no generic arguments",
+        ));
+    }
+
+    Ok(quote_spanned! {marker.span()=>
+        {
+            #[allow(unused_imports)]
+            use #himark::__private::no_dispatch as __himark_dispatch;
+            {
+                #[allow(unused_imports)]
+                use #marker as __himark_dispatch;
+                __himark_dispatch! { #value, #param, #body }
+            }
+        }
+    })
+}
@@ -1,15 +1,30 @@
+//! Sealed markers can't be applied in other crates, neither with `#[mark]` nor with `denmark!`.
+//!
+//! ```compile_fail
+//! // error[E0277]: `Texture` cannot be marked with a sealed marker
+//! #[himark::mark(himark_test::sealed::Gpu)]
+//! struct Texture;
+//! ```
+//!
+//! ```compile_fail
+//! struct Texture;
+//!
+//! // error[E0277]: `Texture` cannot be marked with a sealed marker
+//! himark::denmark!(Texture as himark_test::sealed::Gpu);
+//! ```
+
 use std::marker::PhantomData;
 
 use himark as hi;
 
 #[hi::marker]
-trait Array {}
+pub trait Array {}
 
 #[hi::marker]
-trait Uniform {}
+pub trait Uniform {}
 
 #[hi::marker]
-trait V {}
+pub trait V {}
 
 #[hi::mark(Array, Uniform, V)]
 pub struct EmptyStruct;
@@ -17,6 +32,816 @@ pub struct EmptyStruct;
 #[hi::mark(Array, Uniform, V)]
 pub enum EmptyEnum {}
 
+pub trait Plain {}
+
+#[hi::mark(strict = false, Plain, Array)]
+pub struct Lenient;
+
+pub mod super_ {
+    use super::*;
+
+    #[hi::marker]
+    pub trait Auto:
+        Send + Sync + Unpin + std::panic::UnwindSafe + std::panic::RefUnwindSafe
+    {
+    }
+
+    #[hi::marker]
+    pub trait Single: Array {}
+
+    #[hi::marker]
+    pub trait Many: Array + Uniform + V + Send {}
+
+    #[hi::marker]
+    pub trait WhereBound
+    where
+        Self: Single + Sync,
+    {
+    }
+
+    #[hi::marker]
+    pub trait Generic<T, const N: usize>: Single {}
+
+    #[hi::marker]
+    pub trait GenericSuper<'a, T>: Generic<&'a T, 4>
+    where
+        T: Default + 'a,
+    {
+    }
+}
+
+pub mod sealed {
+    use super::*;
+
+    #[hi::marker(sealed, module = crate::sealed)]
+    pub trait Gpu {}
+
+    #[hi::marker(sealed, module = crate::sealed)]
+    pub trait Texture: Gpu + Array {}
+
+    #[hi::mark(Gpu)]
+    pub struct Buffer<T>(PhantomData<T>)
+    where
+        T: Default + Copy;
+
+    pub mod nested {
+        use super::*;
+
+        #[hi::mark(crate::Array, Gpu, Texture)]
+        pub struct Image;
+    }
+}
+
+pub mod args {
+    use super::*;
+
+    #[hi::marker]
+    pub trait Dimension<const N: usize> {}
+
+    #[hi::marker]
+    pub trait Storage<T: ?Sized> {}
+
+    #[hi::marker]
+    pub trait Borrowed<'a> {}
+
+    #[hi::marker(sealed, module = crate::args)]
+    pub trait Layout<const N: usize> {}
+
+    #[hi::mark(Dimension<3>, Storage<T>, Storage<Self>, Borrowed<'a>, Layout<N>)]
+    pub struct Tensor<'a, T, const N: usize>(PhantomData<&'a [T; N]>);
+
+    #[hi::mark(Storage<[T]>, crate::args::Dimension<{ 2 + 2 }>)]
+    pub struct Slice<T>(PhantomData<T>)
+    where
+        T: Copy;
+}
+
+pub mod conditional {
+    use super::*;
+
+    #[hi::mark(Array where T: Array, Uniform, V where T: Uniform, B: Copy)]
+    pub struct Wrapper<T, B>(PhantomData<(T, B)>);
+
+    #[hi::mark(args::Storage<T> where T: Copy, Array)]
+    pub struct Generic<T: Default, const N: usize>(PhantomData<[T; N]>)
+    where
+        [T; N]: Sized;
+
+    const _: fn() = || {
+        fn assert_marked<T: Array + Uniform + V>() {}
+        assert_marked::<Wrapper<EmptyStruct, u8>>();
+    };
+}
+
+pub mod bounds {
+    use super::*;
+
+    #[hi::marker]
+    pub trait Pod {}
+
+    impl Pod for u8 {}
+    impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+    #[hi::mark(Pod, Array, bounds = "params")]
+    pub struct Params<T, U>(PhantomData<(T, U)>);
+
+    #[hi::mark(Pod, bounds = "fields")]
+    pub struct Fields<T, const N: usize> {
+        pub len: u8,
+        pub data: [T; N],
+        pub first: T,
+    }
+
+    #[hi::mark(Pod, bounds = "fields")]
+    pub enum Either<L, R> {
+        Left(L),
+        Right { value: R },
+        Neither,
+    }
+
+    #[hi::mark(Pod, Uniform where T: Copy, bounds = "fields")]
+    pub union Bits<T: Copy> {
+        pub raw: [u8; 4],
+        pub value: T,
+    }
+
+    const _: fn() = || {
+        fn assert_pod<T: Pod>() {}
+        fn assert_array<T: Array>() {}
+        assert_pod::<Params<u8, [u8; 2]>>();
+        assert_array::<Params<EmptyStruct, EmptyEnum>>();
+        assert_pod::<Fields<u8, 4>>();
+        assert_pod::<Either<u8, [u8; 2]>>();
+        assert_pod::<Bits<u8>>();
+    };
+}
+
+pub mod implied {
+    use super::*;
+
+    #[hi::marker(implies)]
+    pub trait Matrix: Array + Uniform + Send {}
+
+    #[hi::marker(implies)]
+    pub trait Square<const N: usize>: Matrix + V + crate::args::Dimension<N> {}
+
+    #[hi::marker(implies)]
+    pub trait Texel<T: ?Sized>: crate::args::Storage<T> + crate::sealed::Gpu {}
+
+    #[hi::mark(Square<4>)]
+    pub struct Identity;
+
+    #[hi::mark(Matrix, Uniform)]
+    pub struct Explicit;
+
+    pub mod other {
+        use super::*;
+
+        #[hi::marker]
+        pub trait Array {}
+    }
+
+    // Traits named the same as implied supertraits are still implemented.
+    #[hi::mark(Matrix, other::Array)]
+    pub struct Shadowed;
+
+    hi::assert_marked!(Shadowed: Array + other::Array + Uniform);
+
+    #[hi::mark(Square<2>, Texel<[u8]> where T: Copy)]
+    pub struct Generic<T>(PhantomData<fn() -> T>);
+
+    const _: fn() = || {
+        fn assert_square<T: Array + Uniform + V + Matrix + args::Dimension<N>, const N: usize>() {}
+        fn assert_texel<T: args::Storage<[u8]> + sealed::Gpu>() {}
+        assert_square::<Identity, 4>();
+        assert_square::<Generic<u8>, 2>();
+        assert_texel::<Generic<u8>>();
+        assert_marked::<Explicit>();
+        fn assert_marked<T: Array + Uniform + Matrix>() {}
+    };
+}
+
+pub mod denmark {
+    use super::implied::Matrix;
+    use super::*;
+
+    pub struct Foreign<T>(PhantomData<T>);
+
+    hi::denmark! {
+        [u8, u16, u32] as Array, Uniform;
+        impl<T: Copy> Vec<T> as Array, Uniform, args::Storage<Self> where T: Send;
+        impl<T> [T] as V;
+        impl<T> [Foreign<T>, Box<Foreign<T>>] as Matrix where T: Send;
+    }
+
+    hi::denmark!(strict = false; [u8; 4] as Plain);
+
+    const _: fn() = || {
+        fn assert_marked<T: ?Sized + Array + Uniform>() {}
+        fn assert_v<T: ?Sized + V>() {}
+        fn assert_storage<T: args::Storage<T>>() {}
+        fn assert_plain<T: Plain>() {}
+        assert_marked::<u16>();
+        assert_marked::<Vec<u8>>();
+        assert_marked::<Box<Foreign<u8>>>();
+        assert_storage::<Vec<u8>>();
+        assert_v::<[u8]>();
+        assert_plain::<[u8; 4]>();
+    };
+}
+
+pub mod bulk {
+    use super::*;
+
+    #[hi::marker]
+    pub trait Scalar {}
+
+    #[hi::marker(sealed, module = crate::bulk)]
+    pub trait Packed {}
+
+    hi::denmark!([u8, f32] as Scalar, Packed);
+
+    hi::tuples!(Scalar);
+    hi::tuples!(Packed, 1..4);
+    hi::arrays!(Scalar, slices, refs);
+    hi::arrays!(Packed);
+    hi::tuples!(Plain, ..=2, strict = false);
+
+    const _: fn() = || {
+        fn assert_scalar<T: ?Sized + Scalar>() {}
+        fn assert_packed<T: ?Sized + Packed>() {}
+        fn assert_plain<T: Plain>() {}
+        assert_scalar::<()>();
+        assert_scalar::<(u8, f32, (u8,), [f32; 3])>();
+        assert_scalar::<(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8)>();
+        assert_scalar::<[u8]>();
+        assert_scalar::<&mut [(u8, f32)]>();
+        assert_packed::<([u8; 2], f32, u8)>();
+        assert_plain::<((), ())>();
+    };
+}
+
+pub mod propagate {
+    use super::*;
+    use std::mem::ManuallyDrop;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[hi::marker(propagate(all_refs, all_smart_pointers, phantom, slice))]
+    pub trait Shared {}
+
+    #[hi::marker(propagate(all))]
+    pub trait Everything {}
+
+    #[hi::marker(sealed, propagate(option, array), module = crate::propagate)]
+    pub trait Owned {}
+
+    #[hi::marker(propagate(box, manually_drop))]
+    pub trait Tagged<const N: usize> {}
+
+    #[hi::mark(Shared, Everything, Owned, Tagged<1>)]
+    pub struct Leaf;
+
+    const _: fn() = || {
+        fn assert_shared<T: ?Sized + Shared>() {}
+        fn assert_everything<T: ?Sized + Everything>() {}
+        fn assert_owned<T: Owned>() {}
+        fn assert_tagged<T: Tagged<1>>() {}
+        assert_shared::<&mut Box<Rc<Arc<[Leaf]>>>>();
+        assert_shared::<PhantomData<&Leaf>>();
+        assert_everything::<Option<[ManuallyDrop<Leaf>; 2]>>();
+        assert_everything::<&[PhantomData<Box<Leaf>>]>();
+        assert_owned::<[Option<Leaf>; 4]>();
+        assert_tagged::<Box<ManuallyDrop<Leaf>>>();
+    };
+}
+
+pub mod exclusive {
+    use super::*;
+
+    #[hi::marker(excludes(Gpu), propagate(option, rc))]
+    pub trait Cpu {}
+
+    #[hi::marker(excludes(Cpu))]
+    pub trait Gpu {}
+
+    #[hi::marker(sealed, excludes(Cpu, Gpu), module = crate::exclusive)]
+    pub trait Host {}
+
+    #[hi::marker(implies)]
+    pub trait Accelerated: Gpu {}
+
+    #[hi::mark(Cpu)]
+    pub struct Core;
+
+    #[hi::mark(Accelerated)]
+    pub struct Shader;
+
+    #[hi::mark(Host)]
+    pub struct Memory<T>(PhantomData<T>);
+
+    hi::denmark! { [u8, u16] as Cpu; impl<T: Gpu> [T] as Gpu }
+
+    const _: fn() = || {
+        fn assert_cpu<T: ?Sized + Cpu>() {}
+        fn assert_gpu<T: ?Sized + Gpu>() {}
+        fn assert_host<T: Host>() {}
+        assert_cpu::<Option<std::rc::Rc<Core>>>();
+        assert_cpu::<u16>();
+        assert_gpu::<[Shader]>();
+        assert_host::<Memory<Core>>();
+    };
+}
+
+pub mod assertions {
+    use super::*;
+    use crate::exclusive::{Core, Cpu, Gpu, Shader};
+
+    hi::assert_marked!(EmptyStruct: Array + Uniform + V);
+    hi::assert_marked!(Core: Cpu + Send + Sync);
+    hi::assert_marked!([Shader]: Gpu);
+    hi::assert_not_marked!(Core: Gpu);
+    hi::assert_not_marked!(Shader: Cpu);
+    hi::assert_not_marked!(Lenient: Uniform);
+    hi::assert_not_marked!(PhantomData<*const u8>: Send);
+
+    pub const fn pinned() {
+        hi::assert_marked!(Option<std::rc::Rc<Core>>: Cpu);
+        hi::assert_not_marked!(Option<Shader>: Cpu + Gpu);
+    }
+}
+
+pub mod queries {
+    use super::*;
+    use crate::exclusive::{Core, Cpu, Gpu, Shader};
+
+    pub const CORE_ON_CPU: bool = hi::is_marked!(Core, Cpu);
+    pub const SHADER_ON_CPU: bool = hi::is_marked!(Shader, Cpu);
+    pub const SHADERS_ON_GPU: bool = hi::is_marked!([Shader], Gpu + Send);
+
+    pub const fn device<const GPU: bool>() -> &'static str {
+        if GPU {
+            "gpu"
+        } else {
+            "cpu"
+        }
+    }
+
+    pub const SHADER_DEVICE: &str = hi::select!(Shader {
+        Cpu => device::<false>(),
+        Gpu => device::<true>(),
+        _ => "none",
+    });
+
+    pub const LENIENT_LAYOUT: u8 = hi::select!(Lenient { Uniform => 1, Array => 2, _ => 0 });
+    pub const PLAIN_LAYOUT: u8 = hi::select!(bool { Uniform => 1, _ => 0 });
+
+    const _: () = assert!(CORE_ON_CPU && !SHADER_ON_CPU && SHADERS_ON_GPU);
+    const _: () = assert!(LENIENT_LAYOUT == 2 && PLAIN_LAYOUT == 0);
+}
+
+pub mod diagnostics {
+    use super::*;
+
+    #[hi::marker(
+        message = "`{Self}` can't be uploaded to the gpu",
+        label = "not uniform"
+    )]
+    pub trait Upload {}
+
+    #[hi::marker(sealed, note = "use `Buffer<{T}, {N}>` instead", module = crate::diagnostics)]
+    pub trait Buffered<'a, T, const N: usize> {}
+
+    #[hi::marker]
+    #[diagnostic::on_unimplemented(message = "`{Self}` is not a vertex")]
+    pub trait Vertex {}
+
+    #[hi::mark(Upload, Buffered<'static, u8, 4>, Vertex)]
+    pub struct Mesh;
+
+    hi::assert_marked!(Mesh: Upload + Buffered<'static, u8, 4> + Vertex);
+}
+
+pub mod helper {
+    use super::*;
+
+    #[hi::marker(implies, macro = texture, module = crate::helper)]
+    pub trait Texture: Array + V {}
+
+    #[hi::marker(sealed, macro = internal, module = crate::helper)]
+    pub trait Internal {}
+
+    pub struct Image<T>(PhantomData<T>);
+
+    texture!(Image<u8>, impl<T: Copy> [T; 2]);
+    internal!(impl<T> Image<T>, u8,);
+
+    hi::assert_marked!(Image<u8>: Texture + Array + V + Internal);
+    hi::assert_marked!([u16; 2]: Texture + Array + V);
+    hi::assert_not_marked!(Image<u16>: Texture);
+}
+
+pub mod unsafe_ {
+    use super::*;
+
+    /// # Safety
+    ///
+    /// Type consists of plain data only.
+    #[hi::marker(propagate(ref, option))]
+    pub unsafe trait Pod {}
+
+    #[hi::marker(implies)]
+    pub trait Handle: Send + Sync {}
+
+    #[hi::mark(unsafe Send, unsafe Sync, unsafe Pod, Handle, reason = "pointer is never dereferenced")]
+    pub struct Raw(pub *const u8);
+
+    #[hi::mark(unsafe Pod where T: Pod, reason = "`Pod` is implemented only for plain data", bounds = "params")]
+    pub struct Wrapper<T>(T);
+
+    hi::assert_marked!(Raw: Send + Sync + Pod + Handle);
+    hi::assert_marked!(Option<&Wrapper<Raw>>: Pod);
+    hi::assert_not_marked!(Wrapper<String>: Pod);
+}
+
+pub mod assert {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    #[hi::mark(assert Send + Sync, Array, assert Array)]
+    pub struct Shared<'a, T: Clone> {
+        pub value: Arc<T>,
+        pub borrowed: &'a [T],
+        pub next: Option<Box<Self>>,
+    }
+
+    #[hi::mark(assert Send, assert Unpin)]
+    pub enum Local<T> {
+        Cell(Cell<T>),
+        Empty,
+    }
+
+    hi::assert_marked!(Shared<'static, u8>: Send + Sync + Array);
+    hi::assert_marked!(Local<u8>: Send + Unpin);
+    hi::assert_not_marked!(Local<u8>: Sync);
+}
+
+pub mod negative {
+    use super::*;
+
+    #[hi::mark(!Send, Array)]
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct Named<T> {
+        pub value: T,
+    }
+
+    #[hi::mark(!Sync, !Unpin)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Tuple(pub u8);
+
+    #[hi::mark(!Send, !Sync)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct Empty {}
+
+    pub fn construct() -> (Named<u8>, Tuple, Empty) {
+        let named = Named {
+            value: 0,
+            _phantom: Named::<u8>::PHANTOM,
+        };
+        let empty = Empty {
+            _phantom: Empty::PHANTOM,
+        };
+        (named, Tuple(0, Tuple::PHANTOM), empty)
+    }
+
+    hi::assert_marked!(Named<u8>: Sync + Unpin + Array);
+    hi::assert_not_marked!(Named<u8>: Send);
+    hi::assert_marked!(Tuple: Send);
+    hi::assert_not_marked!(Tuple: Sync);
+    hi::assert_not_marked!(Tuple: Unpin);
+    hi::assert_marked!(Empty: Unpin);
+    hi::assert_not_marked!(Empty: Send);
+    hi::assert_not_marked!(Empty: Sync);
+}
+
+pub mod introspect {
+    use super::*;
+    use hi::{Marked, MarkerInfo};
+
+    #[hi::marker(implies)]
+    pub trait Shape: Array + Uniform {}
+
+    #[hi::mark(Shape, V, unsafe Send, introspect, reason = "contains no data")]
+    pub struct Square<T>(PhantomData<*const T>);
+
+    #[hi::mark(introspect)]
+    pub struct Unmarked;
+
+    const fn same(a: &str, b: &str) -> bool {
+        let (a, b) = (a.as_bytes(), b.as_bytes());
+        let mut i = 0;
+        while i < a.len() && i < b.len() && a[i] == b[i] {
+            i += 1;
+        }
+        i == a.len() && i == b.len()
+    }
+
+    const MARKERS: &[MarkerInfo] = <Square<u8> as Marked>::MARKERS;
+
+    const _: () = assert!(MARKERS.len() == 5);
+    const _: () = assert!(MARKERS[0].id == MarkerInfo::new("Shape", module_path!()).id);
+    const _: () = assert!(same(MARKERS[1].name, "V"));
+    const _: () = assert!(same(MARKERS[2].module_path, "core::marker"));
+    const _: () = assert!(same(MARKERS[3].name, "Array"));
+    const _: () = assert!(same(MARKERS[4].name, "Uniform"));
+    const _: () = assert!(<Unmarked as Marked>::MARKERS.is_empty());
+}
+
+pub mod set {
+    use super::introspect::{Shape, Square, Unmarked};
+    use super::*;
+    use hi::{Contains, Here, Marked, There};
+
+    fn assert_contains<S: Contains<M, I>, M: ?Sized, I>() {}
+
+    pub fn assert_marked<T: Marked, M: ?Sized, I>()
+    where
+        T::Markers: Contains<M, I>,
+    {
+    }
+
+    pub fn assertions() {
+        assert_contains::<<Square<u8> as Marked>::Markers, dyn Shape, Here>();
+        assert_contains::<<Square<u8> as Marked>::Markers, dyn Send, There<There<Here>>>();
+        assert_contains::<hi::Set![Array, Uniform], dyn Uniform, _>();
+        assert_marked::<Square<u8>, dyn Uniform, _>();
+        assert_marked::<Square<u8>, dyn V, _>();
+    }
+
+    const _: fn() = || {
+        let _: <Unmarked as Marked>::Markers = hi::Nil;
+    };
+}
+
+pub mod caps {
+    use super::*;
+    use hi::{Has, Here, SubsetOf, There, Union, Without};
+
+    #[hi::marker]
+    pub trait Read {}
+
+    #[hi::marker]
+    pub trait Write {}
+
+    #[hi::marker]
+    pub trait Seek {}
+
+    pub struct File<C>(PhantomData<C>);
+
+    impl<C> File<C> {
+        pub fn read<I>(&self)
+        where
+            C: Has<dyn Read, I>,
+        {
+        }
+
+        pub fn narrow<D: SubsetOf<C, Is>, Is>(self) -> File<D> {
+            File(PhantomData)
+        }
+
+        pub fn drop_write<I>(self) -> File<<C as Without<dyn Write, I>>::Output>
+        where
+            C: Without<dyn Write, I>,
+        {
+            File(PhantomData)
+        }
+    }
+
+    pub type ReadWriteSeek = hi::Caps![Read, Write, Seek];
+    pub type ReadWrite = <hi::Caps![Read] as Union<hi::Caps![Write]>>::Output;
+    pub type ReadOnly = <ReadWrite as Without<dyn Write, There<Here>>>::Output;
+
+    pub fn operations() {
+        let file: File<ReadWriteSeek> = File(PhantomData);
+        file.read();
+        let file: File<ReadWrite> = file.narrow();
+        let file: File<hi::Caps![Write, Read]> = file.narrow();
+        let file: File<hi::Caps![Read]> = file.drop_write();
+        let _: File<ReadOnly> = file;
+    }
+}
+
+#[cfg(feature = "registry")]
+pub mod registry {
+    use super::*;
+    use core::any::TypeId;
+
+    #[hi::marker(registry)]
+    pub trait Plugin {}
+
+    #[hi::marker(implies)]
+    pub trait Effect: Plugin {}
+
+    #[hi::mark(Effect)]
+    pub struct Reverb;
+
+    #[hi::mark(Plugin)]
+    pub struct Chain<T>(PhantomData<T>);
+
+    hi::denmark!([u16, i16] as Plugin);
+
+    pub fn plugins() -> impl Iterator<Item = &'static hi::TypeEntry> {
+        <dyn Plugin>::implementors()
+    }
+
+    pub fn is_plugin(type_id: TypeId) -> bool {
+        <dyn Plugin>::is_implemented_by(type_id)
+    }
+
+    #[test]
+    fn registered() {
+        let mut types: Vec<TypeId> = plugins().map(hi::TypeEntry::type_id).collect();
+        let mut expected = [
+            TypeId::of::<Reverb>(),
+            TypeId::of::<u16>(),
+            TypeId::of::<i16>(),
+        ];
+        types.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(types, expected);
+
+        assert!(is_plugin(TypeId::of::<Reverb>()));
+        // Generic types are not registered.
+        assert!(!is_plugin(TypeId::of::<Chain<u8>>()));
+        assert!(!is_plugin(TypeId::of::<u8>()));
+    }
+}
+
+pub mod meta {
+    use super::*;
+    use hi::MarkerMeta;
+
+    /// Storage of values.
+    ///
+    /// Details which are not a part of the summary.
+    #[hi::marker(implies)]
+    pub trait Storage<T>: Array + Uniform {}
+
+    /// Opaque handles.
+    ///
+    /// # Safety
+    ///
+    /// Handles must not be dereferenced.
+    #[hi::marker(sealed, excludes(Array), module = crate::meta)]
+    pub unsafe trait Opaque: Send {}
+
+    type StorageMeta = dyn Storage<u8>;
+
+    const _: () = assert!(StorageMeta::DOC.len() == "Storage of values.".len());
+    const _: () = assert!(StorageMeta::SUPERTRAITS.len() == 2);
+    const _: () = assert!(StorageMeta::SUPERTRAITS[0].id == <dyn Array as MarkerMeta>::INFO.id);
+    const _: () = assert!(StorageMeta::SUPERTRAITS[1].id == <dyn Uniform as MarkerMeta>::INFO.id);
+    const _: () = assert!(StorageMeta::IMPLIES);
+    const _: () = assert!(!StorageMeta::SEALED && !StorageMeta::EXCLUSIVE && !StorageMeta::UNSAFE);
+
+    const _: () = assert!(<dyn Opaque as MarkerMeta>::DOC.len() == "Opaque handles.".len());
+    const _: () =
+        assert!(<dyn Opaque as MarkerMeta>::SUPERTRAITS[0].id == <dyn Send as MarkerMeta>::INFO.id);
+    const _: () =
+        assert!(<dyn Opaque as MarkerMeta>::SEALED && <dyn Opaque as MarkerMeta>::EXCLUSIVE);
+    const _: () =
+        assert!(<dyn Opaque as MarkerMeta>::UNSAFE && !<dyn Opaque as MarkerMeta>::IMPLIES);
+}
+
+pub mod family {
+    use super::*;
+
+    hi::markers! {
+        /// Colors of the palette.
+        #[marker(sealed, module = crate::family)]
+        pub trait Color: Uniform;
+
+        #[marker(implies)]
+        pub trait Primary<'a>: Color + Uniform where Self: 'a;
+
+        #[marker(excludes(Primary<'static>))]
+        pub trait Secondary: Color;
+
+        /// Primary colors.
+        pub struct Red, Green, Blue: Primary<'static> + Color + Uniform;
+        pub struct Orange: Secondary + Color + Uniform;
+        pub(crate) struct Black;
+    }
+
+    hi::assert_marked!(Red: Primary<'static> + Color + Uniform + Copy + Default + core::hash::Hash);
+    hi::assert_marked!(Orange: Secondary + Color + Uniform + Eq + core::fmt::Debug);
+    hi::assert_not_marked!(Black: Color);
+
+    const _: () = assert!(<dyn Color as hi::MarkerMeta>::SEALED);
+    const _: () = assert!(<dyn Primary<'static> as hi::MarkerMeta>::IMPLIES);
+    const _: () = assert!(<dyn Secondary as hi::MarkerMeta>::EXCLUSIVE);
+}
+
+pub mod reify {
+    use super::*;
+
+    #[hi::reify(module = crate::reify)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Format {
+        /// JavaScript Object Notation.
+        Json,
+        Cbor,
+        Toml = 8,
+    }
+
+    pub fn is_textual<F: FormatMarker>() -> bool {
+        F::VALUE != Format::Cbor
+    }
+
+    pub fn dispatch(format: Format) -> bool {
+        hi::dispatch!(format, |F: FormatMarker| is_textual::<F>())
+    }
+
+    pub fn dispatch_ref(format: &Format) -> usize {
+        hi::dispatch!(
+            format,
+            |F: crate::reify::FormatMarker| core::mem::size_of::<F>()
+        )
+    }
+
+    const _: () = assert!(format::Toml::VALUE as u8 == 8);
+    hi::assert_marked!(format::Json: FormatMarker + Copy + Default + Eq + core::hash::Hash + core::fmt::Debug);
+
+    // Types of variants named like prelude items don't shadow them.
+    // The enum isn't dispatched, so it doesn't need `module`.
+    #[hi::reify]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Reply {
+        Ok,
+        None,
+    }
+
+    pub fn answer(value: Option<u8>) -> Result<Reply, u8> {
+        match value {
+            None => Ok(reply::None::VALUE),
+            Some(_) => Ok(reply::Ok::VALUE),
+        }
+    }
+
+    // Keywords get a trailing underscore.
+    #[hi::reify]
+    pub enum Type {
+        Unit,
+    }
+
+    hi::assert_marked!(type_::Unit: TypeMarker);
+}
+
+pub mod declarative {
+    use super::*;
+
+    // Markers declared by the same macro get distinct callbacks.
+    macro_rules! declare {
+        ($($module:ident),*) => {$(
+            pub mod $module {
+                use super::*;
+
+                #[hi::marker(macro = tag, module = crate::declarative::$module)]
+                pub trait Tag {}
+            }
+        )*};
+    }
+
+    declare!(first, second);
+
+    macro_rules! declare_plain {
+        ($module:ident) => {
+            pub mod $module {
+                use super::*;
+
+                #[hi::marker]
+                pub trait Plain {}
+            }
+        };
+    }
+
+    declare_plain!(third);
+    declare_plain!(fourth);
+
+    #[hi::mark(third::Plain, fourth::Plain)]
+    pub struct Plains;
+
+    #[hi::mark(first::Tag, second::Tag)]
+    pub struct Both;
+
+    first::tag!(u8);
+    second::tag!(u8);
+
+    hi::assert_marked!(Both: first::Tag + second::Tag);
+    hi::assert_marked!(u8: first::Tag + second::Tag);
+}
+
 pub mod type_ {
     use super::*;
 
@@ -0,0 +1,86 @@
+//! Compile-time assertions about markers of concrete types.
+
+/// Asserts at compile time that a type is marked with all of the given markers.
+///
+/// Expands to an item, so it can be used both at module level and inside of function bodies and const blocks.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Array {}
+///
+/// #[hi::marker]
+/// trait Uniform {}
+///
+/// #[hi::mark(Array, Uniform)]
+/// struct Matrix<T>(T);
+///
+/// hi::assert_marked!(Matrix<u8>: Array + Uniform);
+///
+/// const _: () = {
+///     hi::assert_marked!(Matrix<()>: Array);
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # use himark as hi;
+/// # #[hi::marker] trait Array {}
+/// # struct Matrix;
+/// // error[E0277]: the trait bound `Matrix: Array` is not satisfied
+/// hi::assert_marked!(Matrix: Array);
+/// ```
+#[macro_export]
+macro_rules! assert_marked {
+    ($ty:ty: $($bounds:tt)+) => {
+        const _: () = {
+            const fn assert_marked<T: ?Sized + $($bounds)+>() {}
+            assert_marked::<$ty>();
+        };
+    };
+}
+
+/// Asserts at compile time that a type is not marked with the given marker.
+///
+/// With several markers, e.g. `assert_not_marked!(T: A + B)`, assertion fails only if type is marked with all of them.
+/// Use separate assertions to check that type is marked with none of them.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Cpu {}
+///
+/// #[hi::marker]
+/// trait Gpu {}
+///
+/// #[hi::mark(Cpu)]
+/// struct Buffer;
+///
+/// hi::assert_not_marked!(Buffer: Gpu);
+/// hi::assert_not_marked!(Buffer: Cpu + Gpu);
+/// ```
+///
+/// ```compile_fail
+/// # use himark as hi;
+/// # #[hi::marker] trait Cpu {}
+/// # #[hi::mark(Cpu)] struct Buffer;
+/// // error[E0080]: evaluation panicked: `Buffer` must not be marked with `Cpu`
+/// hi::assert_not_marked!(Buffer: Cpu);
+/// ```
+#[macro_export]
+macro_rules! assert_not_marked {
+    ($ty:ty: $($bounds:tt)+) => {
+        const _: () = ::core::assert!(
+            !$crate::is_marked!($ty, $($bounds)+),
+            "{}",
+            ::core::concat!(
+                "`",
+                ::core::stringify!($ty),
+                "` must not be marked with `",
+                ::core::stringify!($($bounds)+),
+                "`",
+            ),
+        );
+    };
+}
@@ -0,0 +1,124 @@
+//! Runtime information about markers and the types marked with them.
+
+use core::fmt;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+
+/// Description of a marker trait.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MarkerInfo {
+    /// Name of the trait, without generic arguments.
+    pub name: &'static str,
+    /// Path of the module which defines the trait.
+    pub module_path: &'static str,
+    /// Identifier derived from the module path and the name, stable across compilations.
+    pub id: u64,
+}
+
+impl MarkerInfo {
+    pub const fn new(name: &'static str, module_path: &'static str) -> Self {
+        // FNV-1a of `module_path::name`.
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        let mut id = 0xcbf2_9ce4_8422_2325;
+        let parts = [module_path.as_bytes(), b"::", name.as_bytes()];
+        let mut part = 0;
+        while part < parts.len() {
+            let bytes = parts[part];
+            let mut i = 0;
+            while i < bytes.len() {
+                id ^= bytes[i] as u64;
+                id = id.wrapping_mul(PRIME);
+                i += 1;
+            }
+            part += 1;
+        }
+        MarkerInfo {
+            name,
+            module_path,
+            id,
+        }
+    }
+}
+
+impl fmt::Display for MarkerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.module_path, self.name)
+    }
+}
+
+/// Markers of a type, implemented by `#[mark(..., introspect)]`.
+///
+/// Lists markers of the attribute together with the markers they imply,
+/// regardless of per-marker where clauses.
+pub trait Marked {
+    const MARKERS: &'static [MarkerInfo];
+
+    /// The same markers in the same order as a type-level set, see [`Set!`](crate::Set).
+    type Markers;
+}
+
+/// Metadata of a marker trait, implemented by `#[marker]` for `dyn Trait`.
+///
+/// ```
+/// use himark::MarkerMeta;
+/// # use himark as hi;
+///
+/// #[hi::marker]
+/// trait Array {}
+///
+/// /// Types which can be uploaded to the gpu.
+/// ///
+/// /// More details.
+/// #[hi::marker(sealed, implies)]
+/// trait Uniform: Array {}
+///
+/// type Meta = dyn Uniform;
+/// # fn main() {
+/// assert_eq!(Meta::INFO.name, "Uniform");
+/// assert_eq!(Meta::DOC, "Types which can be uploaded to the gpu.");
+/// assert_eq!(Meta::SUPERTRAITS, [<dyn Array as MarkerMeta>::INFO]);
+/// assert!(Meta::SEALED && Meta::IMPLIES && !Meta::EXCLUSIVE);
+/// # }
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` does not provide marker metadata",
+    label = "not a marker trait",
+    note = "metadata is provided by markers annotated with `#[himark::marker]`"
+)]
+pub trait MarkerMeta {
+    /// Name, module path and id of the marker.
+    const INFO: MarkerInfo;
+    /// First paragraph of the documentation of the marker, empty if there's none.
+    const DOC: &'static str;
+    /// Supertraits of the marker, all of which are markers or auto traits.
+    const SUPERTRAITS: &'static [MarkerInfo];
+    /// Declared with `#[marker(sealed)]`.
+    const SEALED: bool;
+    /// Declared with `#[marker(excludes(...))]`.
+    const EXCLUSIVE: bool;
+    /// Declared with `#[marker(implies)]`.
+    const IMPLIES: bool;
+    /// Declared as `unsafe trait`.
+    const UNSAFE: bool;
+}
+
+macro_rules! auto_trait_meta {
+    ($($module:literal $trait:ident: $unsafe:literal $doc:literal;)*) => {$(
+        impl MarkerMeta for dyn $trait + '_ {
+            const INFO: MarkerInfo = MarkerInfo::new(stringify!($trait), $module);
+            const DOC: &'static str = $doc;
+            const SUPERTRAITS: &'static [MarkerInfo] = &[];
+            const SEALED: bool = false;
+            const EXCLUSIVE: bool = false;
+            const IMPLIES: bool = false;
+            const UNSAFE: bool = $unsafe;
+        }
+    )*};
+}
+
+auto_trait_meta! {
+    "core::marker" Send: true "Types that can be transferred across thread boundaries.";
+    "core::marker" Sync: true "Types for which it is safe to share references between threads.";
+    "core::marker" Unpin: false "Types that do not require any pinning guarantees.";
+    "core::panic" UnwindSafe: false "A marker trait which represents \"panic safe\" types in Rust.";
+    "core::panic" RefUnwindSafe: false "A marker trait representing types where a shared reference is considered unwind safe.";
+}
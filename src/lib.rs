@@ -1,16 +1,176 @@
-
+/// Implements marker traits for types which can't be annotated with `#[mark]`, e.g. foreign ones.
+///
+/// Fallback used without the `attrs` feature, which doesn't support generics nor strict mode.
+/// Leading `strict = ...;` is accepted and ignored, so invocations compile with and without the feature.
+#[cfg(not(feature = "attrs"))]
 #[macro_export]
 macro_rules! denmark {
-    ($ty:ty as $($traits:path),+ $(,)?) => {
+    (strict = $strict:literal; $($input:tt)*) => {
+        $crate::denmark!($($input)*);
+    };
+    (@impl [$($ty:ty),+] $traits:tt) => {
+        $($crate::denmark!(@impl $ty $traits);)+
+    };
+    (@impl $ty:ty [$($traits:path),+]) => {
         $(impl $traits for $ty { })+
     };
-    // ($ty:ty as $( $($segments:ident ::)? $traits:ident),+ $(,)?) => {
-    //     $(impl $($segments:ident ::)? $traits for $ty { })+
-    // };
+    ([$ty:ty, $($tys:ty),* $(,)?] as $($traits:path),+ $(,)?) => {
+        $crate::denmark!(@impl [$ty $(, $tys)*] [$($traits),+]);
+    };
+    ($ty:ty as $($traits:path),+ $(,)?) => {
+        $crate::denmark!(@impl [$ty] [$($traits),+]);
+    };
 }
 
+mod assert;
+mod introspect;
+mod phantom;
+mod query;
+#[cfg(feature = "registry")]
+mod registry;
+mod set;
+
+pub use introspect::{Marked, MarkerInfo, MarkerMeta};
+pub use phantom::{NotSend, NotSync, NotUnpin};
+#[cfg(feature = "registry")]
+pub use registry::TypeEntry;
+pub use set::{Cons, Contains, Has, Here, Nil, SubsetOf, There, Union, Without};
+
 #[cfg(feature = "attrs")]
 extern crate himark_proc;
 
 #[cfg(feature = "attrs")]
-pub use himark_proc::{mark, marker};
+pub use himark_proc::{arrays, denmark, dispatch, mark, marker, markers, reify, tuples};
+
+#[doc(hidden)]
+pub mod __private {
+    //! Implementation details of `himark` attributes, not public API.
+
+    /// Witness that a trait is a marker.
+    ///
+    /// `#[marker]` implements it for `dyn Trait` of every validated trait.
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` is not a marker trait",
+        label = "not a marker trait",
+        note = "annotate the trait with `#[himark::marker]`"
+    )]
+    pub trait Marker {}
+
+    impl Marker for dyn Send + '_ {}
+    impl Marker for dyn Sync + '_ {}
+    impl Marker for dyn Unpin + '_ {}
+    impl Marker for dyn core::panic::UnwindSafe + '_ {}
+    impl Marker for dyn core::panic::RefUnwindSafe + '_ {}
+
+    pub const fn assert_marker<T: ?Sized + Marker>() {}
+
+    /// Supertrait of sealed markers.
+    ///
+    /// `S` is a token type which is private to the crate defining the marker.
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` cannot be marked with a sealed marker",
+        label = "marker is sealed",
+        note = "sealed markers can only be applied with `#[himark::mark]` inside of the crate which defines them"
+    )]
+    pub trait Sealed<S> {}
+
+    /// Gives access to the marker of a token, used to exclude other markers.
+    ///
+    /// `#[marker(excludes(...))]` implements it for the token of the marker.
+    pub trait Token {
+        type Marker: ?Sized;
+    }
+
+    /// Implemented by `dyn Trait` of an exclusive marker for types which are marked with it.
+    ///
+    /// `#[mark]` implements it for every `O`, `#[marker(excludes(Other))]` implements it
+    /// for types marked with `Other` and `O = dyn Other`, which makes the impls overlap.
+    #[diagnostic::on_unimplemented(
+        message = "`{T}` is not marked with exclusive marker `{Self}`",
+        label = "exclusive marker implemented outside of `#[himark::mark]`",
+        note = "exclusive markers can only be applied with `#[himark::mark]` or `himark::denmark!`"
+    )]
+    pub trait Excludes<T: ?Sized, O: ?Sized> {}
+
+    /// `O` of [`Excludes`] which is implemented only by `#[mark]`.
+    pub struct Marked;
+
+    /// Supertrait of exclusive markers.
+    ///
+    /// `S` is a token type of the marker, see [`Token`].
+    pub trait Exclusive<S> {}
+
+    impl<T: ?Sized, S: Token> Exclusive<S> for T where S::Marker: Excludes<T, Marked> {}
+
+    /// Types registered by `#[mark]` for markers declared with `#[marker(registry)]`.
+    #[cfg(feature = "registry")]
+    #[linkme::distributed_slice]
+    pub static REGISTRY: [crate::TypeEntry];
+
+    /// Registered types which list the marker.
+    #[cfg(feature = "registry")]
+    pub fn implementors(
+        marker: crate::MarkerInfo,
+    ) -> impl Iterator<Item = &'static crate::TypeEntry> {
+        REGISTRY
+            .iter()
+            .filter(move |entry| entry.is_marked(&marker))
+    }
+
+    #[cfg(feature = "registry")]
+    pub use linkme;
+
+    /// Emits registry items, which require the `registry` feature.
+    pub use crate::__himark_registry as registry;
+
+    /// Fallback for traits which don't define `#[mark]` callback.
+    pub use crate::__himark_no_callback as no_callback;
+
+    /// Fallback for traits which aren't generated by `#[reify]`.
+    pub use crate::__himark_no_dispatch as no_dispatch;
+
+    #[cfg(feature = "attrs")]
+    pub use himark_proc::__chain as chain;
+
+    #[cfg(feature = "attrs")]
+    pub use himark_proc::__apply as apply;
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __himark_no_callback {
+    ([$($himark:tt)*] [$($state:tt)*]) => {
+        $($himark)*::__private::chain! { [$($himark)*] [$($state)*] }
+    };
+}
+
+#[cfg(feature = "registry")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __himark_registry {
+    (@entry $($item:tt)*) => {
+        $($item)*
+    };
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
+
+/// Rejects `#[marker(registry)]`, entries of marked types are skipped to report the error once.
+#[cfg(not(feature = "registry"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __himark_registry {
+    (@entry $($item:tt)*) => {};
+    ($($item:tt)*) => {
+        ::core::compile_error! { "`#[marker(registry)]` requires the `registry` feature of himark" }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __himark_no_dispatch {
+    ($($input:tt)*) => {
+        ::core::compile_error!("`dispatch!` requires a marker generated by `#[himark::reify]`")
+    };
+}
@@ -0,0 +1,39 @@
+//! Zero-sized field types which opt the containing type out of auto traits.
+//!
+//! `#[mark(!Send, !Sync, !Unpin)]` adds a field of these types to the marked struct.
+
+use core::cell::Cell;
+use core::marker::{PhantomData, PhantomPinned};
+
+/// Field which makes the containing type `!Send`, while keeping it `Sync`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NotSend(PhantomData<*const ()>);
+
+// SAFETY: type has no data which could be shared.
+unsafe impl Sync for NotSend {}
+
+impl NotSend {
+    pub const fn new() -> Self {
+        NotSend(PhantomData)
+    }
+}
+
+/// Field which makes the containing type `!Sync`, while keeping it `Send`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NotSync(PhantomData<Cell<()>>);
+
+impl NotSync {
+    pub const fn new() -> Self {
+        NotSync(PhantomData)
+    }
+}
+
+/// Field which makes the containing type `!Unpin`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NotUnpin(PhantomPinned);
+
+impl NotUnpin {
+    pub const fn new() -> Self {
+        NotUnpin(PhantomPinned)
+    }
+}
@@ -0,0 +1,112 @@
+//! Compile-time queries about markers of concrete types.
+
+/// Evaluates to a `const bool` which tells whether a type is marked with all of the given markers.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Cpu {}
+///
+/// #[hi::marker]
+/// trait Gpu {}
+///
+/// #[hi::mark(Cpu)]
+/// struct Buffer;
+///
+/// const ON_CPU: bool = hi::is_marked!(Buffer, Cpu);
+/// const ON_GPU: bool = hi::is_marked!(Buffer, Gpu);
+///
+/// assert!(ON_CPU && !ON_GPU);
+/// assert!(hi::is_marked!(Buffer, Cpu + Send + Sync));
+/// ```
+///
+/// # Generic contexts
+///
+/// The query is resolved where the macro is invoked, not after monomorphization.
+/// For a generic parameter it only takes the bounds declared for the parameter into account,
+/// so it's `true` if the parameter is bounded with the markers and `false` otherwise,
+/// regardless of the type the function is eventually called with.
+///
+/// ```
+/// # use himark as hi;
+/// # #[hi::marker] trait Cpu {}
+/// # #[hi::mark(Cpu)] struct Buffer;
+/// fn unbounded<T>() -> bool {
+///     hi::is_marked!(T, Cpu)
+/// }
+///
+/// fn bounded<T: Cpu>() -> bool {
+///     hi::is_marked!(T, Cpu)
+/// }
+///
+/// assert!(!unbounded::<Buffer>());
+/// assert!(bounded::<Buffer>());
+/// ```
+///
+/// Use trait bounds to branch on markers of generic parameters instead.
+#[macro_export]
+macro_rules! is_marked {
+    ($ty:ty, $($bounds:tt)+) => {{
+        // Inherent associated items take precedence over trait ones,
+        // but only when the bounds of the inherent impl are satisfied.
+        #[allow(dead_code)]
+        trait NotMarked {
+            const IS_MARKED: bool = false;
+        }
+
+        impl<T: ?Sized> NotMarked for T {}
+
+        struct Probe<T: ?Sized>(::core::marker::PhantomData<T>);
+
+        #[allow(dead_code)]
+        impl<T: ?Sized + $($bounds)+> Probe<T> {
+            const IS_MARKED: bool = true;
+        }
+
+        <Probe<$ty>>::IS_MARKED
+    }};
+}
+
+/// Selects an expression according to markers of a type.
+///
+/// Arms are checked in order, the first one whose marker is implemented by the type is selected
+/// and `_` arm is selected when none of them is. All expressions must have the same type,
+/// since every one of them is type checked. It can be used in const contexts.
+///
+/// Markers are queried with [`is_marked!`](crate::is_marked), so the same limitations apply in generic contexts.
+///
+/// ```
+/// use himark as hi;
+///
+/// #[hi::marker]
+/// trait Cpu {}
+///
+/// #[hi::marker]
+/// trait Gpu {}
+///
+/// #[hi::mark(Gpu)]
+/// struct Texture;
+///
+/// const DEVICE: &str = hi::select!(Texture {
+///     Gpu => "gpu",
+///     Cpu => "cpu",
+///     _ => "unknown",
+/// });
+///
+/// assert_eq!(DEVICE, "gpu");
+/// assert_eq!(hi::select!(u8 { Gpu => 1, _ => 0 }), 0);
+/// ```
+#[macro_export]
+macro_rules! select {
+    ($ty:ty { _ => $fallback:expr $(,)? }) => {
+        $fallback
+    };
+    ($ty:ty { $marker:path => $expr:expr, $($arms:tt)+ }) => {
+        if $crate::is_marked!($ty, $marker) {
+            $expr
+        } else {
+            $crate::select!($ty { $($arms)+ })
+        }
+    };
+}
@@ -0,0 +1,54 @@
+//! Runtime registry of types marked with markers declared with `#[marker(registry)]`.
+
+use core::any::TypeId;
+use core::fmt;
+
+use crate::MarkerInfo;
+
+/// Type registered by `#[mark]` or `denmark!` with a marker declared with `#[marker(registry)]`.
+///
+/// Entries are collected into a linker section, so the registry covers every crate linked into the binary.
+#[derive(Clone, Copy)]
+pub struct TypeEntry {
+    name: fn() -> &'static str,
+    type_id: fn() -> TypeId,
+    markers: &'static [MarkerInfo],
+}
+
+impl TypeEntry {
+    #[doc(hidden)]
+    pub const fn new<T: ?Sized + 'static>(markers: &'static [MarkerInfo]) -> Self {
+        TypeEntry {
+            name: core::any::type_name::<T>,
+            type_id: TypeId::of::<T>,
+            markers,
+        }
+    }
+
+    /// Name of the type, as returned by [`core::any::type_name`].
+    pub fn name(&self) -> &'static str {
+        (self.name)()
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        (self.type_id)()
+    }
+
+    /// Markers implemented by the attribute which registered the type, together with the markers they imply.
+    pub fn markers(&self) -> &'static [MarkerInfo] {
+        self.markers
+    }
+
+    pub fn is_marked(&self, marker: &MarkerInfo) -> bool {
+        self.markers.iter().any(|info| info.id == marker.id)
+    }
+}
+
+impl fmt::Debug for TypeEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypeEntry")
+            .field("name", &self.name())
+            .field("markers", &self.markers)
+            .finish()
+    }
+}
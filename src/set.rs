@@ -0,0 +1,213 @@
+//! Type-level sets of markers.
+//!
+//! Sets are lists of `dyn Marker` types, so any marker can be an element.
+//! Stable Rust can't tell two types apart, hence operations which look up a marker
+//! take its position as a parameter, e.g. `I` of [`Contains<M, I>`](Contains).
+//! Positions are inferred from concrete sets, generic code declares them as parameters of its own.
+
+use core::marker::PhantomData;
+
+/// Empty marker set.
+pub struct Nil;
+
+/// Marker set with `dyn H` followed by the markers of `T`, usually written with [`Set!`](crate::Set).
+pub struct Cons<H: ?Sized, T>(PhantomData<fn() -> *const H>, PhantomData<T>);
+
+/// Index of the first marker of a set, see [`Contains`].
+pub struct Here;
+
+/// Index of a marker which follows the first one, see [`Contains`].
+pub struct There<I>(PhantomData<I>);
+
+/// Implemented by marker sets which contain marker `M`, given as `dyn Marker`.
+///
+/// `I` is the position of the marker in the set, which is always inferred.
+/// Generic code declares it as a parameter of its own.
+///
+/// ```
+/// use himark::{self as hi, Contains, Marked};
+///
+/// #[hi::marker]
+/// trait Cpu {}
+///
+/// #[hi::marker]
+/// trait Gpu {}
+///
+/// #[hi::mark(Cpu, Gpu, introspect)]
+/// struct Buffer;
+///
+/// fn upload<T: Marked, I>(_: T)
+/// where
+///     T::Markers: Contains<dyn Gpu, I>,
+/// {
+/// }
+///
+/// upload(Buffer);
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "marker set `{Self}` does not contain `{M}`",
+    label = "missing `{M}`"
+)]
+pub trait Contains<M: ?Sized, I> {}
+
+impl<M: ?Sized, T> Contains<M, Here> for Cons<M, T> {}
+
+impl<M: ?Sized, H: ?Sized, T, I> Contains<M, There<I>> for Cons<H, T> where T: Contains<M, I> {}
+
+/// Type-level set of markers, e.g. `Set![Array, Uniform]` is `Cons<dyn Array, Cons<dyn Uniform, Nil>>`.
+///
+/// ```
+/// use himark::{self as hi, Contains, Here, There};
+///
+/// #[hi::marker]
+/// trait Array {}
+///
+/// #[hi::marker]
+/// trait Uniform {}
+///
+/// fn contains<S: Contains<M, I>, M: ?Sized, I>() {}
+///
+/// contains::<hi::Set![Array, Uniform], dyn Uniform, There<Here>>();
+/// ```
+#[macro_export]
+macro_rules! Set {
+    () => {
+        $crate::Nil
+    };
+    ($marker:path $(, $markers:path)* $(,)?) => {
+        $crate::Cons<dyn $marker, $crate::Set![$($markers),*]>
+    };
+}
+
+/// Alias of [`Set!`](crate::Set) for sets of capabilities.
+///
+/// ```
+/// use core::marker::PhantomData;
+/// use himark::{self as hi, Has, SubsetOf};
+///
+/// #[hi::marker]
+/// trait Read {}
+///
+/// #[hi::marker]
+/// trait Write {}
+///
+/// struct File<C>(PhantomData<C>);
+///
+/// impl<C> File<C> {
+///     fn read<I>(&self) where C: Has<dyn Read, I> {}
+///
+///     fn narrow<D: SubsetOf<C, Is>, Is>(self) -> File<D> {
+///         File(PhantomData)
+///     }
+/// }
+///
+/// let file: File<hi::Caps![Read, Write]> = File(PhantomData);
+/// let file: File<hi::Caps![Read]> = file.narrow();
+/// file.read();
+/// ```
+///
+/// ```compile_fail
+/// # use core::marker::PhantomData;
+/// # use himark::{self as hi, Has};
+/// # #[hi::marker] trait Read {}
+/// # #[hi::marker] trait Write {}
+/// # struct File<C>(PhantomData<C>);
+/// # impl<C> File<C> { fn read<I>(&self) where C: Has<dyn Read, I> {} }
+/// let file: File<hi::Caps![Write]> = File(PhantomData);
+/// // error[E0277]: marker set `Nil` does not contain `(dyn Read + 'static)`
+/// file.read();
+/// ```
+#[macro_export]
+macro_rules! Caps {
+    ($($markers:tt)*) => {
+        $crate::Set![$($markers)*]
+    };
+}
+
+/// Bound on a set which contains marker `M`, same as [`Contains`].
+#[diagnostic::on_unimplemented(
+    message = "marker set `{Self}` does not contain `{M}`",
+    label = "missing `{M}`"
+)]
+pub trait Has<M: ?Sized, I>: Contains<M, I> {}
+
+impl<S: Contains<M, I>, M: ?Sized, I> Has<M, I> for S {}
+
+/// Removes marker `M` at position `I` from a set which contains it.
+///
+/// ```
+/// use core::marker::PhantomData;
+/// use himark::{self as hi, Here, There, Without};
+///
+/// #[hi::marker]
+/// trait Read {}
+///
+/// #[hi::marker]
+/// trait Write {}
+///
+/// // Positions can't be inferred in type aliases.
+/// type ReadOnly = <hi::Caps![Read, Write] as Without<dyn Write, There<Here>>>::Output;
+/// let _: PhantomData<ReadOnly> = PhantomData::<hi::Caps![Read]>;
+/// ```
+pub trait Without<M: ?Sized, I> {
+    type Output;
+}
+
+impl<M: ?Sized, T> Without<M, Here> for Cons<M, T> {
+    type Output = T;
+}
+
+impl<M: ?Sized, H: ?Sized, T, I> Without<M, There<I>> for Cons<H, T>
+where
+    T: Without<M, I>,
+{
+    type Output = Cons<H, T::Output>;
+}
+
+/// Markers of both sets, the ones of `Self` first.
+///
+/// Sets have to be disjoint, markers which are in both of them are listed twice
+/// and looking them up is ambiguous.
+///
+/// ```
+/// use core::marker::PhantomData;
+/// use himark::{self as hi, Union};
+///
+/// #[hi::marker]
+/// trait Read {}
+///
+/// #[hi::marker]
+/// trait Write {}
+///
+/// type ReadWrite = <hi::Caps![Read] as Union<hi::Caps![Write]>>::Output;
+/// let _: PhantomData<ReadWrite> = PhantomData::<hi::Caps![Read, Write]>;
+/// ```
+pub trait Union<S> {
+    type Output;
+}
+
+impl<S> Union<S> for Nil {
+    type Output = S;
+}
+
+impl<H: ?Sized, T: Union<S>, S> Union<S> for Cons<H, T> {
+    type Output = Cons<H, T::Output>;
+}
+
+/// Implemented by sets whose every marker is contained in `S`, e.g. for narrowing conversions.
+///
+/// `Is` lists positions of the markers in `S`, see [`Contains`].
+#[diagnostic::on_unimplemented(
+    message = "marker set `{Self}` is not a subset of `{S}`",
+    label = "not a subset of `{S}`"
+)]
+pub trait SubsetOf<S, Is> {}
+
+impl<S> SubsetOf<S, Nil> for Nil {}
+
+impl<H: ?Sized, T, S, I, Is> SubsetOf<S, Cons<I, Is>> for Cons<H, T>
+where
+    S: Contains<H, I>,
+    T: SubsetOf<S, Is>,
+{
+}